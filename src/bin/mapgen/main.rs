@@ -0,0 +1,181 @@
+//! Builds levels without the game and prints them, so generated maps can be
+//! looked over by the hundred.
+mod stats;
+
+use std::process;
+
+use dungeon_crawler::prelude::*;
+use serde::Serialize;
+
+/// One level as written by `--json`.
+#[derive(Serialize)]
+struct LevelReport<'a> {
+    seed: u64,
+    /// Counting from 1, as on the command line.
+    depth: u32,
+    architect: &'a str,
+    theme: &'a str,
+    width: i32,
+    height: i32,
+    /// Rows of the map, drawn with the theme's glyphs and no markers.
+    tiles: Vec<String>,
+    player_start: Point,
+    amulet_start: Point,
+    vaults: &'a [PlacedVault],
+    monster_spawns: &'a [Point],
+}
+
+fn main() {
+    let architects = ArchitectRegistry::default();
+    let options = match MapgenOptions::parse(std::env::args().skip(1), &architects) {
+        Ok(options) => options,
+        Err(CliError::Help(usage)) => {
+            println!("{usage}");
+            return;
+        }
+        Err(err) => {
+            eprintln!("{err}\n\n{MAPGEN_USAGE}");
+            process::exit(2);
+        }
+    };
+    let config = options.config;
+    let data = GameData::load(&config, architects).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    let first_seed = options.seed.unwrap_or_else(random_seed);
+    if options.stats {
+        let names = match &config.architect {
+            Some(name) => vec![name.as_str()],
+            None => data.architects.names(),
+        };
+        let stats: Vec<stats::ArchitectStats> = names
+            .into_iter()
+            .map(|name| {
+                // Only one architect is asked for at a time, so none is rolled for.
+                let config = GameConfig {
+                    architect: Some(name.to_string()),
+                    ..config.clone()
+                };
+                let entry = data.architects.get(name).unwrap();
+                stats::ArchitectStats::measure(entry, first_seed, options.count, &config, &data)
+            })
+            .collect();
+        if options.json {
+            for row in &stats {
+                println!("{}", serde_json::to_string(row).unwrap());
+            }
+        } else {
+            stats::print_table(&stats);
+        }
+        return;
+    }
+
+    let depth = config.start_level;
+    let level = data.plan.level(depth);
+    for seed in (0..options.count as u64).map(|k| first_seed.wrapping_add(k)) {
+        // A new run's first roll is for its first level, so this matches it.
+        let mut rand = RandomNumberGenerator::seeded(seed);
+        let entry = MapBuilder::pick_architect(&mut rand, &config, &data.architects, level, depth);
+        let mb = MapBuilder::build(&mut rand, &config, entry, &data.vaults, level, depth);
+
+        if options.json {
+            let report = LevelReport {
+                seed,
+                depth: depth + 1,
+                architect: entry.name,
+                theme: mb.themes.name(),
+                width: mb.map.width,
+                height: mb.map.height,
+                tiles: rows(&mb, None),
+                player_start: mb.player_start,
+                amulet_start: mb.amulet_start,
+                vaults: &mb.vaults,
+                monster_spawns: &mb.monster_spawns,
+            };
+            println!("{}", serde_json::to_string(&report).unwrap());
+        } else {
+            print_level(seed, depth, entry.name, &mb, &data.templates);
+        }
+    }
+}
+
+fn print_level(seed: u64, depth: u32, architect: &str, mb: &MapBuilder, templates: &Templates) {
+    println!(
+        "seed {seed}, level {}: {architect} architect, {} theme",
+        depth + 1,
+        mb.themes.name()
+    );
+    let vaults: Vec<String> = mb
+        .vaults
+        .iter()
+        .map(|vault| {
+            format!(
+                "{} at ({}, {}) {}x{}",
+                vault.name,
+                vault.area.x1,
+                vault.area.y1,
+                vault.area.width(),
+                vault.area.height()
+            )
+        })
+        .collect();
+    println!(
+        "start ({}, {}), goal ({}, {}), {} spawn points{}",
+        mb.player_start.x,
+        mb.player_start.y,
+        mb.amulet_start.x,
+        mb.amulet_start.y,
+        mb.monster_spawns.len(),
+        vaults
+            .iter()
+            .map(|vault| format!(", {vault}"))
+            .collect::<String>()
+    );
+    for row in rows(mb, Some(templates)) {
+        println!("{row}");
+    }
+    println!();
+}
+
+/// The map's rows in its theme's glyphs. Given the templates, the player's
+/// start, the amulet and the spawn points are marked on them, with what the
+/// vaults hold drawn as the templates they name.
+fn rows(mb: &MapBuilder, markers: Option<&Templates>) -> Vec<String> {
+    let vault_spawn = |point: Point| {
+        mb.vaults
+            .iter()
+            .flat_map(|vault| &vault.spawns)
+            .find(|(at, _)| *at == point)
+            .map(|(_, spawn)| spawn)
+    };
+    (0..mb.map.height)
+        .map(|y| {
+            (0..mb.map.width)
+                .map(|x| {
+                    let point = Point::new(x, y);
+                    let tile = mb.map.tiles[mb.map.point2d_to_index(point)];
+                    let glyph = to_char(mb.themes.tile_to_render(tile) as u8);
+                    let Some(templates) = markers else {
+                        return glyph;
+                    };
+                    match (tile, vault_spawn(point)) {
+                        _ if point == mb.player_start => '@',
+                        // Stairs down keep their own glyph.
+                        (TileType::Floor, _) if point == mb.amulet_start => '|',
+                        (_, Some(VaultSpawn::Monster)) => 'm',
+                        (_, Some(VaultSpawn::Item)) => '!',
+                        (_, Some(VaultSpawn::Template(name))) => templates
+                            .entities
+                            .iter()
+                            .find(|template| &template.name == name)
+                            .map_or('?', |template| template.glyph),
+                        _ if mb.monster_spawns.contains(&point) => 'm',
+                        _ => glyph,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+//! Measurements for `--stats`, taken over many levels per architect.
+use std::time::{Duration, Instant};
+
+use dungeon_crawler::prelude::*;
+use serde::Serialize;
+
+/// What was measured on one level.
+struct Sample {
+    floor_ratio: f32,
+    regions: usize,
+    path_length: f32,
+    vault: bool,
+    spawns: usize,
+    time: Duration,
+}
+
+/// Averages over the levels one architect built.
+#[derive(Serialize)]
+pub struct ArchitectStats {
+    pub architect: &'static str,
+    pub levels: u32,
+    /// Share of the finished level's tiles that can be walked on.
+    pub floor_ratio: f32,
+    /// Separate walkable areas in the architect's own level, before the
+    /// build walls in anything the player can't reach. Architects that wall
+    /// in those areas themselves, `cave_halls` and `wfc` among the built-in
+    /// ones, always leave exactly 1.
+    pub regions: f32,
+    pub max_regions: usize,
+    /// Cost of the path from the player's start to the goal, counting hard
+    /// going and hazards as the monsters do.
+    pub path_length: f32,
+    /// Share of levels a prefab vault was placed in.
+    pub vault_rate: f32,
+    pub spawns: f32,
+    /// Time to build a finished level, including any rebuilds.
+    pub mean_ms: f32,
+    pub max_ms: f32,
+}
+
+impl ArchitectStats {
+    /// Builds `count` levels with `entry`'s architect, from seeds counting up
+    /// from `first_seed`.
+    pub fn measure(
+        entry: &ArchitectEntry,
+        first_seed: u64,
+        count: u32,
+        config: &GameConfig,
+        data: &GameData,
+    ) -> Self {
+        let samples: Vec<Sample> = (0..count as u64)
+            .map(|k| sample(entry, first_seed.wrapping_add(k), config, data))
+            .collect();
+        let mean = |value: &dyn Fn(&Sample) -> f32| {
+            samples.iter().map(value).sum::<f32>() / samples.len().max(1) as f32
+        };
+        Self {
+            architect: entry.name,
+            levels: count,
+            floor_ratio: mean(&|sample| sample.floor_ratio),
+            regions: mean(&|sample| sample.regions as f32),
+            max_regions: samples
+                .iter()
+                .map(|sample| sample.regions)
+                .max()
+                .unwrap_or(0),
+            path_length: mean(&|sample| sample.path_length),
+            vault_rate: mean(&|sample| if sample.vault { 1.0 } else { 0.0 }),
+            spawns: mean(&|sample| sample.spawns as f32),
+            mean_ms: mean(&|sample| sample.time.as_secs_f32() * 1000.0),
+            max_ms: samples
+                .iter()
+                .map(|sample| sample.time.as_secs_f32() * 1000.0)
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+fn sample(entry: &ArchitectEntry, seed: u64, config: &GameConfig, data: &GameData) -> Sample {
+    let depth = config.start_level;
+    let level = data.plan.level(depth);
+
+    // The architect's own level, from the same rolls the finished level's
+    // first attempt starts with.
+    let raw = (entry.build)().new(
+        &mut RandomNumberGenerator::seeded(seed),
+        config.map_width,
+        config.map_height,
+        level.monsters,
+    );
+
+    let started = Instant::now();
+    let mb = MapBuilder::build(
+        &mut RandomNumberGenerator::seeded(seed),
+        config,
+        entry,
+        &data.vaults,
+        level,
+        depth,
+    );
+    let time = started.elapsed();
+
+    let map = &mb.map;
+    let floor = (0..map.tiles.len())
+        .filter(|idx| map.can_enter_tile(map.index_to_point2d(*idx)))
+        .count();
+    let dijkstra_map = DijkstraMap::new(
+        map.width,
+        map.height,
+        &[map.point2d_to_index(mb.player_start)],
+        map,
+        map.max_path_depth(),
+    );
+    Sample {
+        floor_ratio: floor as f32 / map.tiles.len() as f32,
+        regions: count_regions(&raw.map),
+        path_length: dijkstra_map.map[map.point2d_to_index(mb.amulet_start)],
+        vault: !mb.vaults.is_empty(),
+        spawns: mb.monster_spawns.len(),
+        time,
+    }
+}
+
+/// Walkable areas that can't be reached from one another.
+fn count_regions(map: &Map) -> usize {
+    let mut seen = vec![false; map.tiles.len()];
+    let mut regions = 0;
+    for start in 0..map.tiles.len() {
+        if seen[start] || !map.can_enter_tile(map.index_to_point2d(start)) {
+            continue;
+        }
+        regions += 1;
+        seen[start] = true;
+        let mut open = vec![start];
+        while let Some(idx) = open.pop() {
+            for (next, _) in map.get_available_exits(idx) {
+                if !seen[next] {
+                    seen[next] = true;
+                    open.push(next);
+                }
+            }
+        }
+    }
+    regions
+}
+
+/// The table `--stats` prints, one architect to a row.
+pub fn print_table(stats: &[ArchitectStats]) {
+    println!(
+        "{:<12} {:>6} {:>6} {:>8} {:>5} {:>6} {:>6} {:>6} {:>9} {:>8}",
+        "architect",
+        "levels",
+        "floor",
+        "regions",
+        "most",
+        "path",
+        "vault",
+        "spawns",
+        "mean ms",
+        "max ms"
+    );
+    for row in stats {
+        println!(
+            "{:<12} {:>6} {:>5.1}% {:>8.1} {:>5} {:>6.1} {:>5.0}% {:>6.1} {:>9.2} {:>8.2}",
+            row.architect,
+            row.levels,
+            row.floor_ratio * 100.0,
+            row.regions,
+            row.max_regions,
+            row.path_length,
+            row.vault_rate * 100.0,
+            row.spawns,
+            row.mean_ms,
+            row.max_ms
+        );
+    }
+}
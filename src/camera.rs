@@ -1,6 +1,9 @@
 #![allow(clippy::pedantic, dead_code)]
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct Camera {
     pub left_x: i32,
     pub right_x: i32,
@@ -0,0 +1,440 @@
+use std::fmt;
+
+use crate::prelude::*;
+
+pub const USAGE: &str = "\
+Usage: dungeon_crawler [OPTIONS]
+
+Options:
+  --seed <N>             Start a new run from this seed
+  --depth <N>            Start on dungeon level N (default: 1)
+  --architect <NAME>     Build every level with one architect:
+                         rooms, automata, drunkard, bsp, wfc, maze,
+                         voronoi, labyrinth, cave_halls, lakes, volcanic,
+                         empty
+  --theme <NAME>         Draw every level with one theme: dungeon, forest,
+                         cave, desert, ice, crypt, swamp, lava
+  --templates <PATH>     Load monsters and items from PATH
+                         (default: resources/template.ron)
+  --plan <PATH>          Load the dungeon's levels from PATH
+                         (default: resources/dungeon.ron)
+  --vaults <PATH>        Load prefab vaults from PATH
+                         (default: resources/vaults.ron)
+  --map-size <WxH>       Build levels of this size, from 20x20 to 200x200
+                         (default: 80x50)
+  --scale <N>            Scale the window by N, from 0.25 to 8 (default: 1.0)
+  --replay <PATH>        Play back a recorded run
+  --headless             Play without a window
+  --games <N>            Headless games to play (default: 1)
+  --max-turns <N>        Turn limit per headless game (default: 5000)
+  -h, --help             Show this message
+
+Any of --seed, --depth, --architect, --theme, --templates, --plan,
+--vaults or --map-size starts a new run instead of resuming a saved game.";
+
+pub const MAPGEN_USAGE: &str = "\
+Usage: mapgen [OPTIONS]
+
+Builds levels the way a new run would and prints them, without opening a
+window. Level N of --seed S is the level a run started with --seed S and
+--depth N begins on.
+
+Options:
+  --seed <N>             Build the first level from this seed; level k of
+                         --count is built from seed N + k (default: random)
+  --count <N>            Levels to build (default: 1)
+  --depth <N>            Build dungeon level N (default: 1)
+  --architect <NAME>     Build with one architect, as in the game
+  --theme <NAME>         Draw with one theme, as in the game
+  --plan <PATH>          Load the dungeon's levels from PATH
+                         (default: resources/dungeon.ron)
+  --vaults <PATH>        Load prefab vaults from PATH
+                         (default: resources/vaults.ron)
+  --map-size <WxH>       Build levels of this size, from 20x20 to 200x200
+                         (default: 80x50)
+  --json                 Print one JSON object per level instead of a map
+  --stats                Build --count levels with each architect, or the
+                         one given with --architect, and print averages of
+                         their floor, disconnected regions, path to the
+                         goal, vaults, spawn points and build time
+  -h, --help             Show this message
+
+Maps are drawn with their theme's glyphs, with @ where the player starts,
+| for the amulet and m for each monster spawn point. What prefab vaults
+hold is drawn as m for a monster, ! for an item, or the glyph of the
+template they name.";
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    /// Help was asked for; holds the usage text of the binary that parsed it.
+    Help(&'static str),
+    MissingValue(String),
+    InvalidValue {
+        flag: String,
+        value: String,
+    },
+    /// A well-formed value outside the range the flag allows, described
+    /// by `range`.
+    OutOfRange {
+        flag: String,
+        value: String,
+        range: String,
+    },
+    UnknownFlag(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help(usage) => write!(f, "{usage}"),
+            CliError::MissingValue(flag) => write!(f, "{flag} needs a value"),
+            CliError::InvalidValue { flag, value } => {
+                write!(f, "\"{value}\" is not a valid value for {flag}")
+            }
+            CliError::OutOfRange { flag, value, range } => {
+                write!(f, "{flag} must be {range}, not {value}")
+            }
+            CliError::UnknownFlag(flag) => write!(f, "unknown option {flag}"),
+        }
+    }
+}
+
+/// Parsed command line.
+#[derive(Debug)]
+pub struct Options {
+    pub seed: Option<u64>,
+    pub config: GameConfig,
+    /// Set when a flag that shapes the run was given, so a saved game
+    /// shouldn't be resumed in its place.
+    pub new_run: bool,
+    pub scale: f32,
+    pub replay: Option<String>,
+    pub headless: bool,
+    pub games: u32,
+    pub max_turns: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            config: GameConfig::default(),
+            new_run: false,
+            scale: 1.0,
+            replay: None,
+            headless: false,
+            games: 1,
+            max_turns: 5000,
+        }
+    }
+}
+
+impl Options {
+    /// Parses the game's arguments. `--architect` has to name one of
+    /// `architects`.
+    pub fn parse<I>(args: I, architects: &ArchitectRegistry) -> Result<Self, CliError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or(CliError::MissingValue(flag.clone()));
+            match flag.as_str() {
+                "-h" | "--help" => return Err(CliError::Help(USAGE)),
+                "--headless" => options.headless = true,
+                "--seed" => {
+                    options.seed = Some(parse_value(&flag, &value()?)?);
+                    options.new_run = true;
+                }
+                "--depth" => {
+                    let value = value()?;
+                    let depth: u32 = parse_value(&flag, &value)?;
+                    // Whether the dungeon is that deep is checked once its plan is loaded.
+                    if depth < 1 {
+                        return Err(invalid(&flag, &value));
+                    }
+                    options.config.start_level = depth - 1;
+                    options.new_run = true;
+                }
+                "--architect" => {
+                    let value = value()?;
+                    if !architects.contains(&value) {
+                        return Err(invalid(&flag, &value));
+                    }
+                    options.config.architect = Some(value);
+                    options.new_run = true;
+                }
+                "--theme" => {
+                    let value = value()?;
+                    if !THEME_NAMES.contains(&value.as_str()) {
+                        return Err(invalid(&flag, &value));
+                    }
+                    options.config.theme = Some(value);
+                    options.new_run = true;
+                }
+                "--templates" => {
+                    options.config.templates = value()?;
+                    options.new_run = true;
+                }
+                "--plan" => {
+                    options.config.plan = value()?;
+                    options.new_run = true;
+                }
+                "--vaults" => {
+                    options.config.vaults = value()?;
+                    options.new_run = true;
+                }
+                "--map-size" => {
+                    let (width, height) = map_size(&flag, &value()?)?;
+                    options.config.map_width = width;
+                    options.config.map_height = height;
+                    options.new_run = true;
+                }
+                "--scale" => {
+                    let value = value()?;
+                    let scale: f32 = parse_value(&flag, &value)?;
+                    if !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+                        let range = format!("from {MIN_SCALE} to {MAX_SCALE}");
+                        return Err(out_of_range(&flag, &value, range));
+                    }
+                    options.scale = scale;
+                }
+                "--replay" => options.replay = Some(value()?),
+                "--games" => options.games = parse_value(&flag, &value()?)?,
+                "--max-turns" => options.max_turns = parse_value(&flag, &value()?)?,
+                _ => return Err(CliError::UnknownFlag(flag)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Parsed command line of the `mapgen` level inspector.
+#[derive(Debug)]
+pub struct MapgenOptions {
+    pub seed: Option<u64>,
+    pub count: u32,
+    pub config: GameConfig,
+    pub json: bool,
+    pub stats: bool,
+}
+
+impl Default for MapgenOptions {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            count: 1,
+            config: GameConfig::default(),
+            json: false,
+            stats: false,
+        }
+    }
+}
+
+impl MapgenOptions {
+    /// Parses `mapgen`'s arguments. `--architect` has to name one of
+    /// `architects`.
+    pub fn parse<I>(args: I, architects: &ArchitectRegistry) -> Result<Self, CliError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = MapgenOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or(CliError::MissingValue(flag.clone()));
+            match flag.as_str() {
+                "-h" | "--help" => return Err(CliError::Help(MAPGEN_USAGE)),
+                "--json" => options.json = true,
+                "--stats" => options.stats = true,
+                "--seed" => options.seed = Some(parse_value(&flag, &value()?)?),
+                "--count" => options.count = parse_value(&flag, &value()?)?,
+                "--depth" => {
+                    let value = value()?;
+                    let depth: u32 = parse_value(&flag, &value)?;
+                    if depth < 1 {
+                        return Err(invalid(&flag, &value));
+                    }
+                    options.config.start_level = depth - 1;
+                }
+                "--architect" => {
+                    let value = value()?;
+                    if !architects.contains(&value) {
+                        return Err(invalid(&flag, &value));
+                    }
+                    options.config.architect = Some(value);
+                }
+                "--theme" => {
+                    let value = value()?;
+                    if !THEME_NAMES.contains(&value.as_str()) {
+                        return Err(invalid(&flag, &value));
+                    }
+                    options.config.theme = Some(value);
+                }
+                "--plan" => options.config.plan = value()?,
+                "--vaults" => options.config.vaults = value()?,
+                "--map-size" => {
+                    let (width, height) = map_size(&flag, &value()?)?;
+                    options.config.map_width = width;
+                    options.config.map_height = height;
+                }
+                _ => return Err(CliError::UnknownFlag(flag)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn invalid(flag: &str, value: &str) -> CliError {
+    CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn out_of_range(flag: &str, value: &str, range: String) -> CliError {
+    CliError::OutOfRange {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        range,
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    value.parse().map_err(|_| invalid(flag, value))
+}
+
+/// The narrowest and shortest level `--map-size` allows: room for the
+/// largest vault inside the outer wall.
+const MIN_MAP_SIZE: i32 = MAX_VAULT_SIZE as i32 + 2;
+/// The widest and tallest level `--map-size` allows. Some architects take
+/// seconds to build levels much bigger, and far bigger ones have more tiles
+/// than an `i32` can count.
+const MAX_MAP_SIZE: i32 = 200;
+
+/// The smallest `--scale`, which still draws tiles 8 pixels across.
+const MIN_SCALE: f32 = 0.25;
+const MAX_SCALE: f32 = 8.0;
+
+/// Parses `WIDTH`x`HEIGHT`.
+fn parse_size(value: &str) -> Option<(i32, i32)> {
+    let (width, height) = value.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// The size `value` asks `flag` for, from `MIN_MAP_SIZE` to `MAX_MAP_SIZE`
+/// each way.
+fn map_size(flag: &str, value: &str) -> Result<(i32, i32), CliError> {
+    let (width, height) = parse_size(value).ok_or_else(|| invalid(flag, value))?;
+    let sizes = MIN_MAP_SIZE..=MAX_MAP_SIZE;
+    if !sizes.contains(&width) || !sizes.contains(&height) {
+        let range = format!("from {MIN_MAP_SIZE}x{MIN_MAP_SIZE} to {MAX_MAP_SIZE}x{MAX_MAP_SIZE}");
+        return Err(out_of_range(flag, value, range));
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        let args = args.iter().map(|arg| arg.to_string());
+        Options::parse(args, &ArchitectRegistry::default())
+    }
+
+    fn rejected(args: &[&str]) -> CliError {
+        parse(args).unwrap_err()
+    }
+
+    #[test]
+    fn no_arguments_resume_the_saved_game() {
+        let options = parse(&[]).unwrap();
+        assert!(!options.new_run);
+        assert_eq!(options.seed, None);
+        assert_eq!(options.games, 1);
+    }
+
+    #[test]
+    fn run_flags_start_a_new_run() {
+        let options = parse(&["--seed", "7", "--depth", "2", "--architect", "maze"]).unwrap();
+        assert!(options.new_run);
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.config.start_level, 1);
+        assert_eq!(options.config.architect.as_deref(), Some("maze"));
+
+        let options = parse(&["--vaults", "mine.ron"]).unwrap();
+        assert!(options.new_run);
+        assert_eq!(options.config.vaults, "mine.ron");
+    }
+
+    #[test]
+    fn display_flags_keep_the_saved_game() {
+        let options = parse(&["--scale", "1.5", "--headless", "--games", "3"]).unwrap();
+        assert!(!options.new_run);
+        assert_eq!(options.scale, 1.5);
+        assert!(options.headless);
+        assert_eq!(options.games, 3);
+    }
+
+    #[test]
+    fn help_carries_the_usage() {
+        assert_eq!(rejected(&["--seed", "1", "-h"]), CliError::Help(USAGE));
+        let mapgen = MapgenOptions::parse(["--help".to_string()], &ArchitectRegistry::default());
+        assert_eq!(mapgen.unwrap_err(), CliError::Help(MAPGEN_USAGE));
+    }
+
+    #[test]
+    fn rejects_missing_values_and_unknown_flags() {
+        let seed = "--seed".to_string();
+        assert_eq!(rejected(&["--seed"]), CliError::MissingValue(seed));
+        let fast = "--fast".to_string();
+        assert_eq!(rejected(&["--fast"]), CliError::UnknownFlag(fast));
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let cases = [
+            ("--seed", "-1"),
+            ("--depth", "0"),
+            ("--architect", "caverns"),
+            ("--theme", "neon"),
+            ("--map-size", "80"),
+        ];
+        for (flag, value) in cases {
+            assert_eq!(rejected(&[flag, value]), invalid(flag, value));
+        }
+    }
+
+    #[test]
+    fn rejects_scales_too_small_or_large_to_draw() {
+        for value in ["0", "0.01", "100", "NaN"] {
+            let error = rejected(&["--scale", value]);
+            assert!(matches!(error, CliError::OutOfRange { .. }), "{value}");
+        }
+        assert_eq!(parse(&["--scale", "0.25"]).unwrap().scale, MIN_SCALE);
+    }
+
+    #[test]
+    fn map_sizes_leave_room_for_the_largest_vault() {
+        assert_eq!(map_size("--map-size", "80x50"), Ok((80, 50)));
+        assert_eq!(
+            map_size("--map-size", "20x20"),
+            Ok((MIN_MAP_SIZE, MIN_MAP_SIZE))
+        );
+        assert_eq!(parse_size("80"), None);
+        assert_eq!(parse_size("80x"), None);
+    }
+
+    #[test]
+    fn rejects_map_sizes_out_of_range() {
+        for value in ["19x50", "80x19", "201x50", "50000x50000"] {
+            let error = map_size("--map-size", value).unwrap_err();
+            assert!(matches!(error, CliError::OutOfRange { .. }), "{value}");
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fmt, fs::File};
+
+use ron::de::from_reader;
+use serde::de::DeserializeOwned;
+
+/// One thing wrong with one entry of a data file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// What the file's entries are called, such as "entry" or "level".
+    pub label: &'static str,
+    /// Position of the entry in the file, counting from 1.
+    pub entry: usize,
+    /// The entry's name, for files whose entries have one.
+    pub name: Option<String>,
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.label, self.entry)?;
+        if let Some(name) = &self.name {
+            write!(f, " (\"{name}\")")?;
+        }
+        write!(f, ", {}: {}", self.field, self.message)
+    }
+}
+
+/// Why a data file couldn't be loaded. The messages are written to follow
+/// the file's path.
+#[derive(Debug)]
+pub enum DataFileError {
+    Io(std::io::Error),
+    Parse(String),
+    /// The file lists no entries, where it needs at least one. Holds what
+    /// they are called.
+    Empty(&'static str),
+    Invalid(Vec<Problem>),
+}
+
+impl fmt::Display for DataFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataFileError::Io(err) => write!(f, "couldn't be read: {err}"),
+            DataFileError::Parse(err) => write!(f, "is malformed: {err}"),
+            DataFileError::Empty(label) => write!(f, "has no {label}s"),
+            DataFileError::Invalid(problems) => {
+                write!(f, "has {} problem(s):", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads the data file at `path`, without validating it.
+pub fn read_data_file<T: DeserializeOwned>(path: &str) -> Result<T, DataFileError> {
+    let file = File::open(path).map_err(DataFileError::Io)?;
+    from_reader(file).map_err(|err| DataFileError::Parse(err.to_string()))
+}
+
+/// `data`, if validating it turned up no `problems`.
+pub fn valid<T>(data: T, problems: Vec<Problem>) -> Result<T, DataFileError> {
+    if problems.is_empty() {
+        Ok(data)
+    } else {
+        Err(DataFileError::Invalid(problems))
+    }
+}
+
+/// The problems found while validating a data file, entry by entry.
+pub struct Problems {
+    label: &'static str,
+    found: Vec<Problem>,
+}
+
+impl Problems {
+    /// Starts collecting problems for a file whose entries are called `label`.
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            found: Vec::new(),
+        }
+    }
+
+    /// Where problems with the entry at `entry`, counting from 1, are added.
+    pub fn entry(&mut self, entry: usize, name: Option<&str>) -> EntryProblems<'_> {
+        EntryProblems {
+            problems: self,
+            entry,
+            name: name.map(str::to_string),
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<Problem> {
+        self.found
+    }
+}
+
+/// The problems found with one entry of a data file.
+pub struct EntryProblems<'a> {
+    problems: &'a mut Problems,
+    entry: usize,
+    name: Option<String>,
+}
+
+impl EntryProblems<'_> {
+    pub fn add(&mut self, field: &'static str, message: String) {
+        self.problems.found.push(Problem {
+            label: self.problems.label,
+            entry: self.entry,
+            name: self.name.clone(),
+            field,
+            message,
+        });
+    }
+}
+
+/// Where `problems` were found, as entries and fields, for tests to compare.
+#[cfg(test)]
+pub fn problem_locations(problems: Vec<Problem>) -> Vec<(usize, &'static str)> {
+    problems
+        .into_iter()
+        .map(|problem| (problem.entry, problem.field))
+        .collect()
+}
@@ -0,0 +1,234 @@
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+pub const DEFAULT_PLAN: &str = "resources/dungeon.ron";
+
+/// What the player has to find to leave a level.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum LevelGoal {
+    /// Stairs down to the next level.
+    Stairs,
+    /// The Amulet of Yala, which wins the game.
+    Amulet,
+}
+
+/// How one depth of the dungeon is generated.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LevelPlan {
+    /// Registered architect names, each with a relative weight. Left empty,
+    /// the level is built by whichever registered architects cover its depth.
+    #[serde(default)]
+    pub architects: Vec<(String, u32)>,
+    /// Theme names from `THEME_NAMES`, picked between evenly.
+    pub themes: Vec<String>,
+    /// The most monsters and items the level is populated with.
+    pub monsters: usize,
+    /// The most prefab vaults stamped into the level; one if left out.
+    #[serde(default = "one_vault")]
+    pub vaults: usize,
+    /// Whether a vault's `Exit` may hold the level's goal instead of the
+    /// spot its architect picked. Left out, vault exits are plain floor.
+    #[serde(default)]
+    pub vault_goal: bool,
+    pub goal: LevelGoal,
+}
+
+fn one_vault() -> usize {
+    1
+}
+
+/// Every level of the dungeon, from the top down.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DungeonPlan {
+    pub levels: Vec<LevelPlan>,
+}
+
+impl DungeonPlan {
+    /// Reads the plan at `path` and validates it against `architects`.
+    pub fn load(path: &str, architects: &ArchitectRegistry) -> Result<Self, DataFileError> {
+        let plan: DungeonPlan = read_data_file(path)?;
+        if plan.levels.is_empty() {
+            return Err(DataFileError::Empty("level"));
+        }
+        let problems = plan.validate(architects);
+        valid(plan, problems)
+    }
+
+    /// Every problem that would otherwise surface when a level is generated.
+    pub fn validate(&self, architects: &ArchitectRegistry) -> Vec<Problem> {
+        let mut problems = Problems::new("level");
+        let last = self.levels.len();
+
+        for (idx, level) in self.levels.iter().enumerate() {
+            let mut problem = problems.entry(idx + 1, None);
+
+            let depth = idx as u32;
+            if level.architects.is_empty() && architects.available(depth).next().is_none() {
+                problem.add(
+                    "architects",
+                    "lists no architects, and none are registered for this depth".to_string(),
+                );
+            }
+            for (name, weight) in &level.architects {
+                if !architects.contains(name) {
+                    problem.add(
+                        "architects",
+                        format!(
+                            "unknown architect \"{name}\" (expected one of {})",
+                            architects.names().join(", ")
+                        ),
+                    );
+                }
+                if *weight == 0 {
+                    problem.add("architects", format!("\"{name}\" has a weight of 0"));
+                }
+            }
+            let total: u64 = level
+                .architects
+                .iter()
+                .map(|(_, weight)| u64::from(*weight))
+                .sum();
+            if total > MAX_TOTAL_WEIGHT {
+                problem.add(
+                    "architects",
+                    format!(
+                        "weights add up to {total}, but can add up to at most {MAX_TOTAL_WEIGHT}"
+                    ),
+                );
+            }
+
+            if level.themes.is_empty() {
+                problem.add("themes", "must list at least one theme".to_string());
+            }
+            for name in &level.themes {
+                if !THEME_NAMES.contains(&name.as_str()) {
+                    problem.add(
+                        "themes",
+                        format!(
+                            "unknown theme \"{name}\" (expected one of {})",
+                            THEME_NAMES.join(", ")
+                        ),
+                    );
+                }
+            }
+
+            match (level.goal, idx + 1 == last) {
+                (LevelGoal::Stairs, true) => {
+                    problem.add(
+                        "goal",
+                        "the last level must hold the Amulet, not Stairs".to_string(),
+                    );
+                }
+                (LevelGoal::Amulet, false) => {
+                    problem.add(
+                        "goal",
+                        "only the last level can hold the Amulet; the levels below it would be unreachable".to_string(),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        problems.into_vec()
+    }
+
+    /// The plan for `depth`, counting from 0 like `Player::map_level`.
+    pub fn level(&self, depth: u32) -> &LevelPlan {
+        &self.levels[depth as usize]
+    }
+}
+
+impl LevelPlan {
+    /// Picks one of the level's architects, by weight, or `None` if it
+    /// lists none.
+    pub fn pick_architect(&self, rand: &mut RandomNumberGenerator) -> Option<&str> {
+        let choices = self.architects.iter();
+        pick_weighted(rand, choices.map(|(name, weight)| (name.as_str(), *weight)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(levels: &str) -> DungeonPlan {
+        ron::from_str(&format!("DungeonPlan(levels: [{levels}])")).unwrap()
+    }
+
+    const STAIRS: &str = r#"LevelPlan(
+        architects: [("rooms", 1)], themes: ["dungeon"], monsters: 10, goal: Stairs,
+    )"#;
+    const AMULET: &str = r#"LevelPlan(
+        architects: [("rooms", 1)], themes: ["dungeon"], monsters: 10, goal: Amulet,
+    )"#;
+
+    fn problems(plan: &DungeonPlan) -> Vec<(usize, &'static str)> {
+        problem_locations(plan.validate(&ArchitectRegistry::default()))
+    }
+
+    #[test]
+    fn shipped_plan_is_valid() {
+        DungeonPlan::load("resources/dungeon.ron", &ArchitectRegistry::default()).unwrap();
+    }
+
+    #[test]
+    fn accepts_stairs_down_to_the_amulet() {
+        assert!(problems(&plan(&format!("{STAIRS}, {STAIRS}, {AMULET}"))).is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_and_unweighted_architects() {
+        let plan = plan(
+            r#"LevelPlan(
+                architects: [("caverns", 1), ("rooms", 0)],
+                themes: ["dungeon"], monsters: 10, goal: Amulet,
+            )"#,
+        );
+        assert_eq!(problems(&plan), [(1, "architects"), (1, "architects")]);
+    }
+
+    #[test]
+    fn rejects_weights_too_heavy_to_roll() {
+        let plan = plan(
+            r#"LevelPlan(
+                architects: [("rooms", 4000000000)], themes: ["dungeon"], monsters: 10, goal: Amulet,
+            )"#,
+        );
+        assert_eq!(problems(&plan), [(1, "architects")]);
+    }
+
+    #[test]
+    fn checks_architects_against_the_registry_given() {
+        let plan = plan(
+            r#"LevelPlan(
+                architects: [("caverns", 1)], themes: ["dungeon"], monsters: 10, goal: Amulet,
+            )"#,
+        );
+        let mut architects = ArchitectRegistry::default();
+        architects.register(
+            "caverns",
+            1,
+            0..=u32::MAX,
+            ArchitectRegistry::default().get("automata").unwrap().build,
+        );
+        assert!(plan.validate(&architects).is_empty());
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_themes() {
+        let plan = plan(
+            r#"LevelPlan(architects: [("rooms", 1)], themes: [], monsters: 10, goal: Stairs),
+            LevelPlan(architects: [("rooms", 1)], themes: ["neon"], monsters: 10, goal: Amulet)"#,
+        );
+        assert_eq!(problems(&plan), [(1, "themes"), (2, "themes")]);
+    }
+
+    #[test]
+    fn puts_the_amulet_on_the_last_level_only() {
+        assert_eq!(
+            problems(&plan(&format!("{AMULET}, {STAIRS}"))),
+            [(1, "goal"), (2, "goal")]
+        );
+    }
+}
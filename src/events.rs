@@ -0,0 +1,104 @@
+use crate::prelude::*;
+
+/// An entity as it was when an event happened. Events are often read after
+/// the entity is gone (a slain monster, a drunk potion), so everything a
+/// reader needs is captured up front.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Actor {
+    pub entity: Entity,
+    pub name: String,
+    pub is_player: bool,
+}
+
+impl Actor {
+    pub fn of<S: EntityStore>(ecs: &S, entity: Entity) -> Self {
+        let entry = ecs.entry_ref(entity).ok();
+        let name = entry
+            .as_ref()
+            .and_then(|entry| entry.get_component::<Name>().ok())
+            .map_or_else(|| "Something".to_string(), |name| name.0.clone());
+        let is_player = entry.is_some_and(|entry| entry.get_component::<Player>().is_ok());
+        Self {
+            entity,
+            name,
+            is_player,
+        }
+    }
+}
+
+/// What an item did when it was used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemEffect {
+    Healed(i32),
+    RevealedMap,
+}
+
+/// Something that happened this turn.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    DamageDealt {
+        attacker: Actor,
+        victim: Actor,
+        amount: i32,
+    },
+    EntityDied {
+        victim: Actor,
+        killer: Actor,
+    },
+    ItemPickedUp {
+        actor: Actor,
+        item: Actor,
+    },
+    ItemDiscarded {
+        actor: Actor,
+        item: Actor,
+    },
+    ItemUsed {
+        actor: Actor,
+        item: Actor,
+        effects: Vec<ItemEffect>,
+    },
+    /// Lava burned, or deep water half drowned, something standing in it.
+    HurtByTerrain {
+        victim: Actor,
+        tile: TileType,
+        amount: i32,
+        killed: bool,
+    },
+    DoorOpened {
+        actor: Actor,
+    },
+    DoorClosed {
+        actor: Actor,
+    },
+    /// `depth` counts from 0, like `Player::map_level`.
+    /// `revisited` is set when the player has been on the level before.
+    LevelEntered {
+        depth: u32,
+        revisited: bool,
+    },
+    PlayerWon,
+}
+
+/// The events raised since the schedule started running. Systems that cause
+/// something push to it; systems that react to it (the message log, and
+/// anything else that wants to know) read it at the end of the schedule,
+/// after which it is cleared.
+#[derive(Clone, Debug, Default)]
+pub struct Events {
+    queue: Vec<GameEvent>,
+}
+
+impl Events {
+    pub fn send(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GameEvent> {
+        self.queue.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
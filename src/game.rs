@@ -0,0 +1,436 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const DEFAULT_TEMPLATES: &str = "resources/template.ron";
+
+/// Everything, other than the seed, that shapes a run. Kept as a resource so
+/// every level of the run is built the same way, and stored with saves and
+/// replays so they come back exactly as they were played.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub start_level: u32,
+    pub architect: Option<String>,
+    pub theme: Option<String>,
+    pub templates: String,
+    pub plan: String,
+    pub vaults: String,
+    pub map_width: i32,
+    pub map_height: i32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            start_level: 0,
+            architect: None,
+            theme: None,
+            templates: DEFAULT_TEMPLATES.to_string(),
+            plan: DEFAULT_PLAN.to_string(),
+            vaults: DEFAULT_VAULTS.to_string(),
+            map_width: DEFAULT_MAP_WIDTH,
+            map_height: DEFAULT_MAP_HEIGHT,
+        }
+    }
+}
+
+/// The data files a run is built from, loaded and checked before it starts,
+/// and the architects its levels can be built with.
+#[derive(Clone, Debug)]
+pub struct GameData {
+    pub templates: Templates,
+    pub plan: DungeonPlan,
+    pub vaults: VaultLibrary,
+    pub architects: ArchitectRegistry,
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    File { path: String, err: DataFileError },
+    Architect(String),
+    StartLevel { level: u32, levels: usize },
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::File { path, err } => write!(f, "{path} {err}"),
+            DataError::Architect(name) => write!(f, "no architect is registered as \"{name}\""),
+            DataError::StartLevel { level, levels } => write!(
+                f,
+                "can't start on level {} of a dungeon with {levels} level(s)",
+                level + 1
+            ),
+        }
+    }
+}
+
+impl GameData {
+    /// Loads and validates the data files named by `config`. The plan's
+    /// architects, and `config`'s, have to be registered in `architects`.
+    pub fn load(config: &GameConfig, architects: ArchitectRegistry) -> Result<Self, DataError> {
+        let file_error = |path: &str| {
+            let path = path.to_string();
+            move |err| DataError::File { path, err }
+        };
+        let templates =
+            Templates::load(&config.templates).map_err(file_error(&config.templates))?;
+        if let Some(name) = &config.architect
+            && !architects.contains(name)
+        {
+            return Err(DataError::Architect(name.clone()));
+        }
+        let plan =
+            DungeonPlan::load(&config.plan, &architects).map_err(file_error(&config.plan))?;
+        let vaults =
+            VaultLibrary::load(&config.vaults, &templates).map_err(file_error(&config.vaults))?;
+        if config.start_level as usize >= plan.levels.len() {
+            return Err(DataError::StartLevel {
+                level: config.start_level,
+                levels: plan.levels.len(),
+            });
+        }
+        Ok(Self {
+            templates,
+            plan,
+            vaults,
+            architects,
+        })
+    }
+
+    /// The data the run in `resources` is being played with.
+    pub fn from_resources(resources: &Resources) -> Self {
+        Self {
+            templates: resources.get::<Templates>().unwrap().clone(),
+            plan: resources.get::<DungeonPlan>().unwrap().clone(),
+            vaults: resources.get::<VaultLibrary>().unwrap().clone(),
+            architects: resources.get::<ArchitectRegistry>().unwrap().clone(),
+        }
+    }
+}
+
+/// The seed a run was started from. Every random roll in the game comes from
+/// the single `RandomNumberGenerator` resource seeded with this value, apart
+/// from those of the `F5` debug key, which stops the replay recording.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Seed(pub u64);
+
+/// Where the current level's monsters and items were spawned, kept so the
+/// level can be repopulated after the templates are edited.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpawnPoints(pub Vec<Point>);
+
+/// Picks a seed for a run when the player didn't ask for one.
+pub fn random_seed() -> u64 {
+    RandomNumberGenerator::new().next_u64()
+}
+
+/// Populates an empty world and resources with the first level of a new run.
+/// `data` should already have been loaded from the files named in `config`.
+pub fn new_game(
+    ecs: &mut World,
+    resources: &mut Resources,
+    seed: u64,
+    config: GameConfig,
+    data: GameData,
+) {
+    let mut rand = RandomNumberGenerator::seeded(seed);
+    let level = config.start_level;
+    let mut map_builder = build_level(ecs, &mut rand, &config, &data, level);
+    // Starting further down, the player arrives by the stairs from the level above.
+    let player_start = if level > 0 {
+        step_off_stairs(&mut map_builder.map, map_builder.player_start)
+    } else {
+        map_builder.player_start
+    };
+    spawn_player(ecs, player_start, level);
+
+    let GameData {
+        templates,
+        plan,
+        vaults,
+        architects,
+    } = data;
+    for _ in 0..2 {
+        spawn_level(
+            ecs,
+            &mut rand,
+            level as usize,
+            &map_builder.monster_spawns,
+            &templates,
+            resources,
+        );
+    }
+    spawn_vaults(
+        ecs,
+        &mut rand,
+        level as usize,
+        &map_builder.vaults,
+        &templates,
+        resources,
+    );
+
+    resources.insert(SpawnPoints(map_builder.monster_spawns));
+    resources.insert(map_builder.map);
+    resources.insert(Camera::new(player_start));
+    resources.insert(TurnState::AwaitingInput);
+    resources.insert(map_builder.themes);
+    resources.insert(LevelStore::default());
+    resources.insert(rand);
+    resources.insert(Seed(seed));
+    resources.insert(Replay::new(seed, config.clone()));
+    resources.insert(config);
+    resources.insert(templates);
+    resources.insert(plan);
+    resources.insert(vaults);
+    resources.insert(architects);
+
+    resources.insert(MessageLog::default());
+
+    let mut events = Events::default();
+    events.send(GameEvent::LevelEntered {
+        depth: level,
+        revisited: false,
+    });
+    resources.insert(events);
+}
+
+/// A level the player has left, kept as it was so it can be walked back into.
+pub struct StoredLevel {
+    pub map: Map,
+    pub theme: Box<dyn MapThemes>,
+    pub spawn_points: SpawnPoints,
+    /// The stairs the player left by, which they come back out of.
+    pub stairs: Point,
+    /// Everything on the level other than the player and what they carry.
+    pub world: World,
+}
+
+/// Every level of the run the player has visited but isn't on, by depth.
+#[derive(Default)]
+pub struct LevelStore {
+    pub levels: BTreeMap<u32, StoredLevel>,
+}
+
+/// Moves the player (and everything they carry) down a level.
+pub fn advance_level(ecs: &mut World, resources: &mut Resources) {
+    let depth = player_level(ecs) + 1;
+    change_level(ecs, resources, depth);
+}
+
+/// Moves the player (and everything they carry) back up a level.
+pub fn return_to_level_above(ecs: &mut World, resources: &mut Resources) {
+    let depth = player_level(ecs) - 1;
+    change_level(ecs, resources, depth);
+}
+
+fn player_level(ecs: &World) -> u32 {
+    <&Player>::query().iter(ecs).next().unwrap().map_level
+}
+
+/// Stores the level the player is on and puts them on the one at `depth`,
+/// next to the stairs that lead back. Levels seen before come back exactly
+/// as they were left; the rest are generated.
+fn change_level(ecs: &mut World, resources: &mut Resources, depth: u32) {
+    // 1. Move everything that isn't the player or carried by them out of the world, into the store.
+    let (left_depth, left_from) = <(&Player, &Point)>::query()
+        .iter(ecs)
+        .map(|(player, pos)| (player.map_level, *pos))
+        .next()
+        .unwrap();
+    let mut left_behind = World::default();
+    left_behind.move_from(ecs, &(!component::<Player>() & !component::<Carried>()));
+
+    let mut store = resources.remove::<LevelStore>().unwrap();
+    store.levels.insert(
+        left_depth,
+        StoredLevel {
+            map: resources.remove::<Map>().unwrap(),
+            theme: resources.remove::<Box<dyn MapThemes>>().unwrap(),
+            spawn_points: resources.remove::<SpawnPoints>().unwrap(),
+            stairs: left_from,
+            world: left_behind,
+        },
+    );
+
+    // 2. Bring the level being entered back, or generate it on the first visit.
+    let revisited = store.levels.contains_key(&depth);
+    let (mut map, theme, spawn_points, stairs) = match store.levels.remove(&depth) {
+        Some(mut level) => {
+            ecs.move_from(&mut level.world, &any());
+            (level.map, level.theme, level.spawn_points, level.stairs)
+        }
+        None => {
+            let config = resources.get::<GameConfig>().unwrap().clone();
+            let data = GameData::from_resources(resources);
+            let mut rng = resources.remove::<RandomNumberGenerator>().unwrap();
+            let map_builder = build_level(ecs, &mut rng, &config, &data, depth);
+            spawn_level(
+                ecs,
+                &mut rng,
+                depth as usize,
+                &map_builder.monster_spawns,
+                &data.templates,
+                resources,
+            );
+            spawn_vaults(
+                ecs,
+                &mut rng,
+                depth as usize,
+                &map_builder.vaults,
+                &data.templates,
+                resources,
+            );
+            resources.insert(rng);
+
+            // Coming down, the player arrives at the way back up; coming up, at the way back down.
+            let stairs = if depth > left_depth {
+                map_builder.player_start
+            } else {
+                map_builder.amulet_start
+            };
+            let spawn_points = SpawnPoints(map_builder.monster_spawns);
+            (map_builder.map, map_builder.themes, spawn_points, stairs)
+        }
+    };
+    resources.insert(store);
+
+    // 3. Step the player off the stairs, and have everyone look around again.
+    let player_pos = step_off_stairs(&mut map, stairs);
+    <(&mut Player, &mut Point)>::query()
+        .iter_mut(ecs)
+        .for_each(|(player, pos)| {
+            player.map_level = depth;
+            *pos = player_pos;
+        });
+    <&mut FieldOfView>::query()
+        .iter_mut(ecs)
+        .for_each(|fov| fov.is_dirty = true);
+
+    resources.insert(spawn_points);
+    resources.insert(map);
+    resources.insert(Camera::new(player_pos));
+    resources.insert(TurnState::AwaitingInput);
+    resources.insert(theme);
+
+    resources
+        .get_mut::<Events>()
+        .unwrap()
+        .send(GameEvent::LevelEntered { depth, revisited });
+}
+
+/// Generates the map for `depth`, and the Amulet of Yala if the plan hides
+/// it there. Stairs are part of the map.
+fn build_level(
+    ecs: &mut World,
+    rng: &mut RandomNumberGenerator,
+    config: &GameConfig,
+    data: &GameData,
+    depth: u32,
+) -> MapBuilder {
+    let level_plan = data.plan.level(depth);
+    let map_builder = MapBuilder::new(
+        rng,
+        config,
+        &data.architects,
+        &data.vaults,
+        level_plan,
+        depth,
+    );
+    if level_plan.goal == LevelGoal::Amulet {
+        spawn_amulet_of_yala(ecs, map_builder.amulet_start);
+    }
+    map_builder
+}
+
+/// The first tile beside `stairs` the player can safely stand on, so that
+/// arriving on a level doesn't take them straight back off it. A closed door
+/// there is opened. Stairs with nowhere safe around them are stood on instead.
+fn step_off_stairs(map: &mut Map, stairs: Point) -> Point {
+    let beside = [
+        Point::new(-1, 0),
+        Point::new(1, 0),
+        Point::new(0, -1),
+        Point::new(0, 1),
+    ]
+    .into_iter()
+    .map(|delta| stairs + delta)
+    .find(|pos| {
+        (map.can_enter_tile(*pos) || map.is_closed_door(*pos))
+            && !matches!(
+                map.tiles[map.point2d_to_index(*pos)],
+                TileType::Exit | TileType::UpStairs | TileType::DeepWater | TileType::Lava
+            )
+    });
+    let Some(pos) = beside else {
+        return stairs;
+    };
+    if map.is_closed_door(pos) {
+        let idx = map.point2d_to_index(pos);
+        map.tiles[idx] = TileType::OpenDoor;
+    }
+    pos
+}
+
+/// Replaces the current level's monsters with fresh ones rolled from the
+/// loaded templates, for trying out template edits without changing level.
+/// Monsters a vault put in place are left where they are. The replay stops
+/// recording, since it can't reproduce the new monsters.
+pub fn respawn_monsters(ecs: &mut World, resources: &mut Resources) {
+    let enemies: Vec<Entity> = <Entity>::query()
+        .filter(component::<Enemy>() & !component::<FromVault>())
+        .iter(ecs)
+        .copied()
+        .collect();
+    for enemy in enemies {
+        ecs.remove(enemy);
+    }
+
+    let (player_pos, map_level) = <(&Point, &Player)>::query()
+        .iter(ecs)
+        .map(|(pos, player)| (*pos, player.map_level))
+        .next()
+        .unwrap();
+    let spawn_points: Vec<Point> = resources
+        .get::<SpawnPoints>()
+        .unwrap()
+        .0
+        .iter()
+        .filter(|pos| **pos != player_pos)
+        .copied()
+        .collect();
+
+    // Replays don't record the key, so it leaves the run's own rolls alone.
+    let seed = resources.get::<Seed>().unwrap().0;
+    let mut rng = RandomNumberGenerator::seeded(seed.rotate_left(32) ^ u64::from(map_level));
+    let templates = resources.get::<Templates>().unwrap().clone();
+    let spawned =
+        templates.spawn_enemies(ecs, &mut rng, map_level as usize, &spawn_points, resources);
+
+    resources
+        .get_mut::<MessageLog>()
+        .unwrap()
+        .add(MessageKind::Info, format!("Respawned {spawned} monsters."));
+    stop_recording(resources, "Monsters were respawned");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_off_stairs_opens_a_door_rather_than_stand_on_them() {
+        let mut map = Map::new(3, 3);
+        map.tiles.iter_mut().for_each(|tile| *tile = TileType::Wall);
+        let stairs = Point::new(1, 1);
+        let door = Point::new(1, 2);
+        let (stairs_idx, door_idx) = (map.point2d_to_index(stairs), map.point2d_to_index(door));
+        map.tiles[stairs_idx] = TileType::Exit;
+        map.tiles[door_idx] = TileType::Door;
+
+        assert_eq!(step_off_stairs(&mut map, stairs), door);
+        assert_eq!(map.tiles[door_idx], TileType::OpenDoor);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{fs, time::SystemTime};
+
+use crate::prelude::*;
+
+/// Frames between checks for edited data files, about once a second.
+pub const RELOAD_POLL_TICKS: u32 = 30;
+
+/// Notices when data files are edited on disk, by polling their modification times.
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    files: Vec<(String, Option<SystemTime>)>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`, if it isn't watched already.
+    pub fn watch(&mut self, path: &str) {
+        if !self.files.iter().any(|(watched, _)| watched == path) {
+            self.files.push((path.to_string(), modified(path)));
+        }
+    }
+
+    /// The watched files whose modification time changed since the last call.
+    pub fn changed(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (path, last_modified) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Re-reads every data file the run uses that was edited since the last call.
+/// A file that no longer loads is reported in the message log, and the
+/// previously loaded version is kept. A file that does reload stops the
+/// replay recording, since the replay can't reproduce the edit.
+pub fn reload_data_files(watcher: &mut FileWatcher, resources: &mut Resources) {
+    let config = resources.get::<GameConfig>().unwrap().clone();
+    watcher.watch(&config.templates);
+    watcher.watch(&config.plan);
+    watcher.watch(&config.vaults);
+
+    for path in watcher.changed() {
+        if path == config.templates {
+            match Templates::load(&path) {
+                Ok(templates) => {
+                    resources.insert(templates);
+                    report_reloaded(resources, &path);
+                }
+                Err(err) => report_failure(resources, &path, &err.to_string()),
+            }
+        } else if path == config.plan {
+            let levels = resources.get::<DungeonPlan>().unwrap().levels.len();
+            let architects = resources.get::<ArchitectRegistry>().unwrap().clone();
+            match DungeonPlan::load(&path, &architects) {
+                // The player may already be on a level a shorter plan wouldn't have.
+                Ok(plan) if plan.levels.len() != levels => report_failure(
+                    resources,
+                    &path,
+                    &format!("can't change its number of levels during a run (it has {levels})"),
+                ),
+                Ok(plan) => {
+                    resources.insert(plan);
+                    report_reloaded(resources, &path);
+                }
+                Err(err) => report_failure(resources, &path, &err.to_string()),
+            }
+        } else if path == config.vaults {
+            let templates = resources.get::<Templates>().unwrap().clone();
+            match VaultLibrary::load(&path, &templates) {
+                Ok(vaults) => {
+                    resources.insert(vaults);
+                    report_reloaded(resources, &path);
+                }
+                Err(err) => report_failure(resources, &path, &err.to_string()),
+            }
+        }
+    }
+}
+
+fn report_reloaded(resources: &Resources, path: &str) {
+    resources
+        .get_mut::<MessageLog>()
+        .unwrap()
+        .add(MessageKind::Info, format!("Reloaded {path}."));
+    stop_recording(resources, &format!("{path} was reloaded"));
+}
+
+fn report_failure(resources: &Resources, path: &str, err: &str) {
+    eprintln!("{path} {err}");
+    let mut log = resources.get_mut::<MessageLog>().unwrap();
+    log.add(MessageKind::Hurt, format!("Couldn't reload {path}:"));
+    for line in err.lines() {
+        log.add(MessageKind::Hurt, line.trim());
+    }
+}
@@ -0,0 +1,47 @@
+#![allow(clippy::pedantic, unused_imports)]
+mod camera;
+mod cli;
+mod components;
+mod data_file;
+mod dungeon_plan;
+mod events;
+mod game;
+mod hot_reload;
+mod map;
+mod map_builder;
+mod message_log;
+mod player;
+mod replay;
+mod save;
+mod simulation;
+mod spawner;
+mod systems;
+mod turn_state;
+mod vaults;
+pub mod prelude {
+    pub use bracket_lib::prelude::*;
+    pub use legion::*;
+    pub const SCREEN_WIDTH: i32 = 80;
+    pub const SCREEN_HEIGHT: i32 = 50;
+    pub use crate::camera::*;
+    pub use crate::cli::*;
+    pub use crate::components::*;
+    pub use crate::data_file::*;
+    pub use crate::dungeon_plan::*;
+    pub use crate::events::*;
+    pub use crate::game::*;
+    pub use crate::hot_reload::*;
+    pub use crate::map::*;
+    pub use crate::map_builder::*;
+    pub use crate::message_log::*;
+    pub use crate::player::*;
+    pub use crate::replay::*;
+    pub use crate::save::*;
+    pub use crate::simulation::*;
+    pub use crate::spawner::*;
+    pub use crate::systems::*;
+    pub use crate::turn_state::*;
+    pub use crate::vaults::*;
+    pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
+    pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
+}
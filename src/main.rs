@@ -1,33 +1,7 @@
 #![allow(clippy::pedantic, unused_imports)]
-mod camera;
-mod components;
-mod map;
-mod map_builder;
-mod player;
-mod spawner;
-mod systems;
-mod turn_state;
-mod prelude {
-    pub use bracket_lib::prelude::*;
-    pub use legion::*;
-    pub const SCREEN_WIDTH: i32 = 80;
-    pub const SCREEN_HEIGHT: i32 = 50;
-    pub use crate::camera::*;
-    pub use crate::components::*;
-    pub use crate::map::*;
-    pub use crate::map_builder::*;
-    pub use crate::player::*;
-    pub use crate::spawner::*;
-    pub use crate::systems::*;
-    pub use crate::turn_state::*;
-    pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
-    pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
-}
-
-use std::{collections::HashSet, process};
+use std::{collections::VecDeque, process};
 
-use legion::systems::{CommandBuffer, Resource};
-use prelude::*;
+use dungeon_crawler::prelude::*;
 
 struct State {
     ecs: World,
@@ -35,48 +9,91 @@ struct State {
     input_system: Schedule,
     player_system: Schedule,
     monster_system: Schedule,
+    playback: Option<VecDeque<VirtualKeyCode>>,
+    playback_ticks: u32,
+    history_scroll: usize,
+    watcher: FileWatcher,
+    reload_ticks: u32,
 }
 
+/// Frames to wait between recorded keys, so a replay can be followed by eye.
+const PLAYBACK_TICKS_PER_TURN: u32 = 5;
+
 impl State {
-    fn new() -> Self {
-        let mut ecs = World::default();
-        let mut resources = Resources::default();
-        let mut rand = RandomNumberGenerator::new();
-        let mut map_builder = MapBuilder::new(&mut rand);
-        spawn_player(&mut ecs, map_builder.player_start);
-        // spawn_amulet_of_yala(&mut ecs, map_builder.amulet_start);
-        let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
-        map_builder.map.tiles[exit_idx] = TileType::Exit;
-
-        spawn_level(
-            &mut ecs,
-            &mut rand,
-            0,
-            &map_builder.monster_spawns,
-            &mut resources,
-        );
-        spawn_level(
-            &mut ecs,
-            &mut rand,
-            0,
-            &map_builder.monster_spawns,
-            &mut resources,
-        );
+    /// Resumes the saved game if there is one, unless a new run was asked for.
+    fn new(seed: Option<u64>, config: GameConfig, data: GameData, new_run: bool) -> Self {
+        let resumed = match new_run {
+            false if has_save() => match load_game(data.architects.clone()) {
+                Ok(game) => {
+                    delete_save();
+                    Some(game)
+                }
+                Err(err) => {
+                    eprintln!("Couldn't resume the saved game: {err}");
+                    eprintln!("Starting a new game instead.");
+                    None
+                }
+            },
+            _ => None,
+        };
 
-        resources.insert(map_builder.map);
-        resources.insert(Camera::new(map_builder.player_start));
-        resources.insert(TurnState::AwaitingInput);
-        resources.insert(map_builder.themes);
+        let (ecs, resources) = resumed.unwrap_or_else(|| {
+            let mut ecs = World::default();
+            let mut resources = Resources::default();
+            new_game(
+                &mut ecs,
+                &mut resources,
+                seed.unwrap_or_else(random_seed),
+                config,
+                data,
+            );
+            (ecs, resources)
+        });
 
         Self {
             ecs,
             resources,
-            input_system: build_input_scheduler(),
-            player_system: build_player_scheduler(),
-            monster_system: build_monster_scheduler(),
+            input_system: build_input_scheduler(true),
+            player_system: build_player_scheduler(true),
+            monster_system: build_monster_scheduler(true),
+            playback: None,
+            playback_ticks: 0,
+            history_scroll: 0,
+            watcher: FileWatcher::default(),
+            reload_ticks: 0,
         }
     }
 
+    /// Starts the recorded run from its seed and plays its keys back. Once the
+    /// recording runs out, the keyboard takes over.
+    fn replaying(replay: &Replay, data: GameData) -> Result<Self, ReplayError> {
+        let keys = replay.keys()?;
+        let mut state = Self::new(Some(replay.seed), replay.config.clone(), data, true);
+        state.playback = Some(keys.into());
+        Ok(state)
+    }
+
+    /// The key to act on this frame: the player's, or the next recorded one.
+    fn next_key(&mut self, ctx: &BTerm, state: TurnState) -> Option<VirtualKeyCode> {
+        let Some(keys) = &mut self.playback else {
+            return ctx.key;
+        };
+        if state != TurnState::AwaitingInput {
+            return None;
+        }
+        if keys.is_empty() {
+            self.playback = None;
+            return ctx.key;
+        }
+
+        self.playback_ticks += 1;
+        if self.playback_ticks < PLAYBACK_TICKS_PER_TURN {
+            return None;
+        }
+        self.playback_ticks = 0;
+        keys.pop_front()
+    }
+
     fn game_over(&mut self, ctx: &mut BTerm) {
         ctx.set_active_console(3);
         ctx.print_color_centered(18, RED, BLACK, "Your quest has ended");
@@ -98,6 +115,7 @@ impl State {
             BLACK,
             "Don't worry, you can always try again with a new hero.",
         );
+        self.print_seed(ctx, 23);
 
         ctx.print_color_centered(27, GREEN, BLACK, "Press 1 to play again.");
         ctx.print_color_centered(29, GREEN, BLACK, "Press 2 to exit.");
@@ -126,6 +144,7 @@ impl State {
             BLACK,
             "Your town is saved, and you can return to your normal life.",
         );
+        self.print_seed(ctx, 24);
         ctx.print_color_centered(27, GREEN, BLACK, "Press 1 to play again..");
         ctx.print_color_centered(28, GREEN, BLACK, "Press 2 to exit..");
 
@@ -137,102 +156,69 @@ impl State {
         }
     }
 
-    fn reset_game_state(&mut self) {
-        self.ecs = World::default();
-        self.resources = Resources::default();
-        let mut rand = RandomNumberGenerator::new();
-        let mut map_builder = MapBuilder::new(&mut rand);
-        spawn_player(&mut self.ecs, map_builder.player_start);
-        // spawn_amulet_of_yala(&mut self.ecs, map_builder.amulet_start);
-        let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
-        map_builder.map.tiles[exit_idx] = TileType::Exit;
-
-        spawn_level(
-            &mut self.ecs,
-            &mut rand,
-            0,
-            &map_builder.monster_spawns,
-            &mut self.resources,
-        );
+    /// Full-screen view of the whole message log, newest at the bottom.
+    fn message_history(&mut self, ctx: &mut BTerm) {
+        const PAGE: usize = (SCREEN_HEIGHT - 6) as usize;
 
-        spawn_level(
-            &mut self.ecs,
-            &mut rand,
-            0,
-            &map_builder.monster_spawns,
-            &mut self.resources,
+        let log = self.resources.get::<MessageLog>().unwrap();
+        let max_scroll = log.len().saturating_sub(PAGE);
+        self.history_scroll = match ctx.key {
+            Some(VirtualKeyCode::Up) => self.history_scroll + 1,
+            Some(VirtualKeyCode::Down) => self.history_scroll.saturating_sub(1),
+            Some(VirtualKeyCode::PageUp) => self.history_scroll + PAGE,
+            Some(VirtualKeyCode::PageDown) => self.history_scroll.saturating_sub(PAGE),
+            Some(VirtualKeyCode::Home) => max_scroll,
+            Some(VirtualKeyCode::End) => 0,
+            _ => self.history_scroll,
+        }
+        .min(max_scroll);
+
+        ctx.set_active_console(3);
+        ctx.print_color_centered(1, YELLOW, BLACK, "Message History");
+        for (y, message) in log.page(self.history_scroll, PAGE).iter().enumerate() {
+            ctx.print_color(1, 3 + y as i32, message.kind.color(), BLACK, &message.text);
+        }
+        ctx.print_color_centered(
+            SCREEN_HEIGHT - 2,
+            CYAN,
+            BLACK,
+            "Up/Down, PgUp/PgDn: Scroll | Esc or L: Close",
         );
+        drop(log);
+
+        if let Some(VirtualKeyCode::Escape | VirtualKeyCode::L) = ctx.key {
+            self.resources.insert(TurnState::AwaitingInput);
+        }
+    }
 
-        self.resources.insert(map_builder.map);
-        self.resources.insert(Camera::new(map_builder.player_start));
-        self.resources.insert(TurnState::AwaitingInput);
-        self.resources.insert(map_builder.themes);
+    fn print_seed(&self, ctx: &mut BTerm, y: i32) {
+        let seed = self.resources.get::<Seed>().unwrap().0;
+        ctx.print_color_centered(y, GRAY, BLACK, format!("Seed: {seed}"));
     }
 
-    fn advanced_level(&mut self) {
-        // 1. Remove all entities from the ECS World that aren’t either the player or items carried by the player
-        let player = *<Entity>::query()
-            .filter(component::<Player>())
-            .iter(&mut self.ecs)
-            .nth(0)
-            .unwrap();
-        // 2. Set the is_dirty flag on the player’s FieldOfView to ensure that the map renders correctly on the next turn
-        let mut entities_to_keep = HashSet::new();
-        entities_to_keep.insert(player);
-
-        <(Entity, &Carried)>::query()
-            .iter(&self.ecs)
-            .filter(|(_, carry)| carry.0 == player)
-            .map(|(entity, _)| *entity)
-            .for_each(|entity| {
-                entities_to_keep.insert(entity);
-            });
-
-        let mut cb = CommandBuffer::new(&mut self.ecs);
-        for e in Entity::query().iter(&self.ecs) {
-            if !entities_to_keep.contains(e) {
-                cb.remove(*e);
+    fn save_and_quit(&mut self, ctx: &mut BTerm) {
+        if self.playback.is_none() {
+            if let Err(err) = save_game(&self.ecs, &self.resources) {
+                eprintln!("Couldn't save the game: {err}");
             }
+            write_replay(&self.resources);
         }
-        cb.flush(&mut self.ecs, &mut self.resources);
-        <&mut FieldOfView>::query()
-            .iter_mut(&mut self.ecs)
-            .for_each(|fov| fov.is_dirty = true);
-
-        // 3. Generate a new level as you did before.
-        let mut rng = RandomNumberGenerator::new();
-        let mut map_builder = MapBuilder::new(&mut rng);
-        let mut map_level = 0;
-        <(&mut Player, &mut Point)>::query()
-            .iter_mut(&mut self.ecs)
-            .for_each(|(player, pos)| {
-                player.map_level += 1;
-                map_level = player.map_level;
-                pos.x = map_builder.player_start.x;
-                pos.y = map_builder.player_start.y;
-            });
-
-        // 4. Check the current level number: if it’s 0 or 1, spawn an exit staircase; if it’s 2, spawn the Amulet of Yala.
-        if map_level == 2 {
-            spawn_amulet_of_yala(&mut self.ecs, map_builder.amulet_start);
-        } else {
-            let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
-            map_builder.map.tiles[exit_idx] = TileType::Exit;
-        }
+        ctx.quit();
+    }
 
-        // 5. Finish setting up spawned monsters and resources as you did before
-        spawn_level(
+    /// Starts a new run, built the same way as the last one.
+    fn reset_game_state(&mut self) {
+        let config = self.resources.get::<GameConfig>().unwrap().clone();
+        let data = GameData::from_resources(&self.resources);
+        self.ecs = World::default();
+        self.resources = Resources::default();
+        new_game(
             &mut self.ecs,
-            &mut rng,
-            map_level as usize,
-            &map_builder.monster_spawns,
             &mut self.resources,
+            random_seed(),
+            config,
+            data,
         );
-
-        self.resources.insert(map_builder.map);
-        self.resources.insert(Camera::new(map_builder.player_start));
-        self.resources.insert(TurnState::AwaitingInput);
-        self.resources.insert(map_builder.themes);
     }
 }
 
@@ -246,13 +232,51 @@ impl GameState for State {
         ctx.cls();
         ctx.set_active_console(3);
         ctx.cls();
-        self.resources.insert(ctx.key);
         ctx.set_active_console(0);
         self.resources.insert(Point::from_tuple(ctx.mouse_pos()));
 
-        let current_state = self.resources.get::<TurnState>().unwrap().clone();
+        self.reload_ticks += 1;
+        if self.reload_ticks >= RELOAD_POLL_TICKS {
+            self.reload_ticks = 0;
+            reload_data_files(&mut self.watcher, &mut self.resources);
+        }
+
+        let current_state = *self.resources.get::<TurnState>().unwrap();
+        let key = self.next_key(ctx, current_state);
+        self.resources.insert(key);
+
+        let mut close_requested = false;
+        INPUT.lock().for_each_message(|event| {
+            if event == BEvent::CloseRequested {
+                close_requested = true;
+            }
+        });
+
+        // A game in progress is saved on the way out; finished ones have nothing to resume.
+        let in_progress = !matches!(current_state, TurnState::GameOver | TurnState::Victory);
+        if in_progress
+            && (close_requested
+                || (current_state == TurnState::AwaitingInput
+                    && ctx.key == Some(VirtualKeyCode::Escape)))
+        {
+            self.save_and_quit(ctx);
+            return;
+        }
+        if close_requested {
+            ctx.quit();
+            return;
+        }
 
         match current_state {
+            // Looking through the log is not a move, so it is neither played nor recorded.
+            TurnState::AwaitingInput if key == Some(VirtualKeyCode::L) => {
+                self.history_scroll = 0;
+                self.resources.insert(TurnState::ShowingLog);
+            }
+            // Debug key: repopulate the level from the (possibly just reloaded) templates.
+            TurnState::AwaitingInput if key == Some(VirtualKeyCode::F5) => {
+                respawn_monsters(&mut self.ecs, &mut self.resources);
+            }
             TurnState::AwaitingInput => self
                 .input_system
                 .execute(&mut self.ecs, &mut self.resources),
@@ -269,8 +293,18 @@ impl GameState for State {
                 self.victory(ctx);
             }
             TurnState::NextLevel => {
-                self.advanced_level();
+                advance_level(&mut self.ecs, &mut self.resources);
             }
+            TurnState::PreviousLevel => {
+                return_to_level_above(&mut self.ecs, &mut self.resources);
+            }
+            TurnState::ShowingLog => self.message_history(ctx),
+        }
+
+        let new_state = *self.resources.get::<TurnState>().unwrap();
+        let finished = matches!(new_state, TurnState::GameOver | TurnState::Victory);
+        if finished && new_state != current_state && self.playback.is_none() {
+            write_replay(&self.resources);
         }
 
         render_draw_buffer(ctx).expect("Render error");
@@ -278,11 +312,64 @@ impl GameState for State {
 }
 
 fn main() -> BError {
+    let architects = ArchitectRegistry::default();
+    let options = match Options::parse(std::env::args().skip(1), &architects) {
+        Ok(options) => options,
+        Err(CliError::Help(usage)) => {
+            println!("{usage}");
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let replay = match &options.replay {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Broken data files are reported up front, rather than when a level is built mid-game.
+    let config = match &replay {
+        Some(replay) => &replay.config,
+        None => &options.config,
+    };
+    let data = GameData::load(config, architects).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+
+    if options.headless {
+        if let Some(replay) = &replay {
+            if let Err(err) = run_headless_replay(replay, &data) {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        run_headless(
+            options.seed.unwrap_or_else(random_seed),
+            options.games,
+            options.max_turns,
+            &options.config,
+            &data,
+        );
+        return Ok(());
+    }
+
+    let tile_size = (32.0 * options.scale).round() as u32;
     let context = BTermBuilder::new()
         .with_title("Dungeon Crawler")
         .with_fps_cap(30.0)
         .with_dimensions(DISPLAY_WIDTH, DISPLAY_HEIGHT)
-        .with_tile_dimensions(32, 32)
+        .with_tile_dimensions(tile_size, tile_size)
         .with_resource_path("resources/")
         .with_font("dungeonfont.png", 32, 32)
         .with_font("terminal8x8.png", 8, 8)
@@ -291,6 +378,12 @@ fn main() -> BError {
         .with_simple_console_no_bg(DISPLAY_WIDTH * 2, DISPLAY_HEIGHT * 2, "terminal8x8.png")
         .with_sparse_console_no_bg(DISPLAY_WIDTH * 2, DISPLAY_HEIGHT * 2, "terminal8x8.png")
         .build()?;
+    INPUT.lock().activate_event_queue();
 
-    main_loop(context, State::new())
+    let state = match &replay {
+        // Keys were already checked when the replay was loaded.
+        Some(replay) => State::replaying(replay, data).unwrap(),
+        None => State::new(options.seed, options.config, data, options.new_run),
+    };
+    main_loop(context, state)
 }
@@ -1,34 +1,82 @@
 #![allow(dead_code, unconditional_recursion)]
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
-const NUM_TILES: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
+/// Size of a level when nothing asks for another one.
+pub const DEFAULT_MAP_WIDTH: i32 = 80;
+pub const DEFAULT_MAP_HEIGHT: i32 = 50;
+
+/// The most a single step can cost on a path, so searches that must reach
+/// every tile of a level can be made deep enough.
+pub const MAX_EXIT_COST: f32 = 8.0;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     Exit,
+    /// Stairs back up to the level above.
+    UpStairs,
+    /// A closed door, which blocks sight until something bumps it open.
+    Door,
+    OpenDoor,
+    ShallowWater,
+    /// Drowns anything that walks into it; swimmers and fliers cross safely.
+    DeepWater,
+    /// Burns anything standing in it that isn't flying.
+    Lava,
+    Rubble,
+    /// Floor over water or lava.
+    Bridge,
+    Grass,
+}
+
+impl TileType {
+    /// What a step onto the tile costs a path, so that monsters go round
+    /// hard going and hazards when they can. Closed doors cost the turn it
+    /// takes to open them.
+    pub fn exit_cost(self) -> f32 {
+        match self {
+            TileType::Door | TileType::ShallowWater | TileType::Rubble => 2.0,
+            TileType::DeepWater => 4.0,
+            TileType::Lava => MAX_EXIT_COST,
+            _ => 1.0,
+        }
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Map {
+    pub width: i32,
+    pub height: i32,
     pub tiles: Vec<TileType>,
     pub revealed_tiles: Vec<bool>,
 }
 
 impl Map {
-    pub fn new() -> Self {
+    pub fn new(width: i32, height: i32) -> Self {
+        let num_tiles = (width * height) as usize;
         Self {
-            tiles: vec![TileType::Floor; NUM_TILES],
-            revealed_tiles: vec![false; NUM_TILES],
+            width,
+            height,
+            tiles: vec![TileType::Floor; num_tiles],
+            revealed_tiles: vec![false; num_tiles],
         }
     }
+    /// Deep enough for a path search to follow a route through every tile,
+    /// so the far end of a long maze isn't mistaken for unreachable.
+    pub fn max_path_depth(&self) -> f32 {
+        (self.width * self.height) as f32 * MAX_EXIT_COST
+    }
+
     /* pub fn render(&self, ctx: &mut BTerm, camera: &Camera) {
            ctx.set_active_console(0);
            // Render the map based on camera position.
            for y in camera.top_y..camera.bottom_y {
                for x in camera.left_x..camera.right_x {
                    if self.in_bound(Point::new(x, y)) {
-                       let idx = map_idx(x, y);
+                       let idx = self.map_idx(x, y);
                        match self.tiles[idx] {
                            TileType::Floor => {
                                ctx.set(
@@ -57,27 +105,85 @@ impl Map {
            }
        }
     */
+    pub fn map_idx(&self, x: i32, y: i32) -> usize {
+        ((y * self.width) + x) as usize
+    }
+
     pub fn in_bound(&self, point: Point) -> bool {
-        point.x >= 0 && point.x < SCREEN_WIDTH && point.y >= 0 && point.y < SCREEN_HEIGHT
+        point.x >= 0 && point.x < self.width && point.y >= 0 && point.y < self.height
     }
+    /// Whether something on foot can step onto `point`.
     pub fn can_enter_tile(&self, point: Point) -> bool {
-        self.in_bound(point)
-            && (self.tiles[map_idx(point.x, point.y)] == TileType::Floor
-                || self.tiles[map_idx(point.x, point.y)] == TileType::Exit)
+        self.can_enter_tile_as(point, Movement::Walks)
+    }
+
+    /// Whether something that gets about by `movement` can step onto
+    /// `point`. Swimmers keep to the water; everything else goes anywhere
+    /// that isn't a wall or a closed door, hazards included.
+    pub fn can_enter_tile_as(&self, point: Point, movement: Movement) -> bool {
+        let Some(idx) = self.try_idx(point) else {
+            return false;
+        };
+        let tile = self.tiles[idx];
+        match movement {
+            Movement::Swims => matches!(
+                tile,
+                TileType::ShallowWater | TileType::DeepWater | TileType::Bridge
+            ),
+            Movement::Walks | Movement::Flies => !matches!(tile, TileType::Wall | TileType::Door),
+        }
+    }
+
+    /// The neighbour of `idx` lowest on `dijkstra_map` that something getting
+    /// about by `movement` can step onto, or bump open if it `opens_doors`.
+    pub fn lowest_exit_as(
+        &self,
+        dijkstra_map: &DijkstraMap,
+        idx: usize,
+        movement: Movement,
+        opens_doors: bool,
+    ) -> Option<usize> {
+        self.get_available_exits(idx)
+            .into_iter()
+            .map(|(exit, _)| exit)
+            .filter(|exit| {
+                let pos = self.index_to_point2d(*exit);
+                self.can_enter_tile_as(pos, movement) || (opens_doors && self.is_closed_door(pos))
+            })
+            .min_by(|a, b| dijkstra_map.map[*a].total_cmp(&dijkstra_map.map[*b]))
+    }
+
+    /// How far every tile is from `start`, for something that walks and opens
+    /// doors when `opens_doors` is set, and goes round them when it isn't.
+    pub fn distances_from(&self, start: Point, opens_doors: bool) -> DijkstraMap {
+        let starts = [self.point2d_to_index(start)];
+        let depth = self.max_path_depth();
+        if opens_doors {
+            DijkstraMap::new(self.width, self.height, &starts, self, depth)
+        } else {
+            DijkstraMap::new(self.width, self.height, &starts, &ShutDoors(self), depth)
+        }
+    }
+
+    pub fn is_closed_door(&self, point: Point) -> bool {
+        self.try_idx(point)
+            .is_some_and(|idx| self.tiles[idx] == TileType::Door)
     }
 
     pub fn try_idx(&self, point: Point) -> Option<usize> {
         if !self.in_bound(point) {
             None
         } else {
-            Some(map_idx(point.x, point.y))
+            Some(self.map_idx(point.x, point.y))
         }
     }
 
     pub fn valid_exit(&self, loc: Point, delta: Point) -> Option<usize> {
         let destination = delta + loc;
         if self.in_bounds(destination) {
-            if self.can_enter_tile(destination) {
+            // Paths lead through closed doors, which the player opens by
+            // bumping them. See `ShutDoors` for those who can't.
+            if self.can_enter_tile(destination) || self.is_closed_door(destination) {
                 let idx = self.point2d_to_index(destination);
                 Some(idx)
             } else {
@@ -89,13 +195,9 @@ impl Map {
     }
 }
 
-pub fn map_idx(x: i32, y: i32) -> usize {
-    ((y * SCREEN_WIDTH) + x) as usize
-}
-
 impl Algorithm2D for Map {
     fn dimensions(&self) -> Point {
-        Point::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+        Point::new(self.width, self.height)
     }
     fn in_bounds(&self, pos: Point) -> bool {
         self.in_bound(pos)
@@ -123,19 +225,19 @@ impl BaseMap for Map {
         let mut exits = SmallVec::new();
         let location = self.index_to_point2d(idx);
         if let Some(idx) = self.valid_exit(location, Point::new(-1, 0)) {
-            exits.push((idx, 1.0));
+            exits.push((idx, self.tiles[idx].exit_cost()));
         }
 
         if let Some(idx) = self.valid_exit(location, Point::new(1, 0)) {
-            exits.push((idx, 1.0))
+            exits.push((idx, self.tiles[idx].exit_cost()))
         }
 
         if let Some(idx) = self.valid_exit(location, Point::new(0, -1)) {
-            exits.push((idx, 1.0))
+            exits.push((idx, self.tiles[idx].exit_cost()))
         }
 
         if let Some(idx) = self.valid_exit(location, Point::new(0, 1)) {
-            exits.push((idx, 1.0))
+            exits.push((idx, self.tiles[idx].exit_cost()))
         }
 
         exits
@@ -147,6 +249,37 @@ impl BaseMap for Map {
     }
 
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx] != TileType::Floor
+        matches!(self.tiles[idx], TileType::Wall | TileType::Door)
+    }
+}
+
+/// The map as seen by something that can't open doors, for path searches
+/// that must go round closed doors instead of through them.
+struct ShutDoors<'a>(&'a Map);
+
+impl Algorithm2D for ShutDoors<'_> {
+    fn dimensions(&self) -> Point {
+        self.0.dimensions()
+    }
+    fn in_bounds(&self, pos: Point) -> bool {
+        self.0.in_bound(pos)
+    }
+}
+
+impl BaseMap for ShutDoors<'_> {
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.0
+            .get_available_exits(idx)
+            .into_iter()
+            .filter(|(exit, _)| self.0.tiles[*exit] != TileType::Door)
+            .collect()
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.0.get_pathing_distance(idx1, idx2)
+    }
+
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.0.is_opaque(idx)
     }
 }
@@ -4,11 +4,18 @@ use crate::prelude::*;
 pub struct CellularAutomataArchitect {}
 
 impl MapArchitect for CellularAutomataArchitect {
-    fn new(&mut self, rand: &mut RandomNumberGenerator) -> MapBuilder {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
         let mut mb = MapBuilder {
-            map: Map::new(),
+            map: Map::new(width, height),
             rooms: Vec::new(),
             monster_spawns: Vec::new(),
+            vaults: Vec::new(),
             player_start: Point::zero(),
             amulet_start: Point::zero(),
             themes: super::themes::DungeonTheme::new(),
@@ -19,7 +26,7 @@ impl MapArchitect for CellularAutomataArchitect {
         }
 
         let start = self.find_start(&mb.map);
-        mb.monster_spawns = mb.spawn_monster(&start, rand);
+        mb.monster_spawns = mb.spawn_monster(&start, rand, monsters);
         mb.player_start = start;
         mb.amulet_start = mb.find_most_distance();
 
@@ -43,7 +50,8 @@ impl CellularAutomataArchitect {
         let mut neighbors = 0;
         for iy in -1..=1 {
             for ix in -1..=1 {
-                if !(ix == 0 && iy == 0) && map.tiles[map_idx(x + ix, y + iy)] == TileType::Wall {
+                if !(ix == 0 && iy == 0) && map.tiles[map.map_idx(x + ix, y + iy)] == TileType::Wall
+                {
                     neighbors += 1
                 }
             }
@@ -54,10 +62,10 @@ impl CellularAutomataArchitect {
     fn iteration(&mut self, map: &mut Map) {
         let mut new_tiles = map.tiles.clone();
 
-        for y in 1..SCREEN_HEIGHT - 1 {
-            for x in 1..SCREEN_WIDTH - 1 {
+        for y in 1..map.height - 1 {
+            for x in 1..map.width - 1 {
                 let neighbours = self.count_neighbours(x, y, map);
-                let idx = map_idx(x, y);
+                let idx = map.map_idx(x, y);
 
                 if neighbours > 4 || neighbours == 0 {
                     new_tiles[idx] = TileType::Wall;
@@ -71,7 +79,7 @@ impl CellularAutomataArchitect {
     }
 
     fn find_start(&mut self, map: &Map) -> Point {
-        let center = Point::new(SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2);
+        let center = Point::new(map.width / 2, map.height / 2);
         let closest_point = map
             .tiles
             .iter()
@@ -0,0 +1,121 @@
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// Partitions narrower than twice this aren't split any further.
+const MIN_PARTITION: i32 = 10;
+const MIN_ROOM: i32 = 3;
+
+/// Splits the map in two, and each half in two again, until the pieces are
+/// too small to split, then puts a room in every piece. Each pair of halves
+/// is joined by a corridor, so every room can be reached and the rooms cover
+/// the whole map evenly.
+pub struct BspArchitect {}
+
+impl MapArchitect for BspArchitect {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
+        let mut mb = MapBuilder {
+            map: Map::new(width, height),
+            rooms: Vec::new(),
+            monster_spawns: Vec::new(),
+            vaults: Vec::new(),
+            player_start: Point::zero(),
+            amulet_start: Point::zero(),
+            themes: super::themes::DungeonTheme::new(),
+        };
+
+        mb.fill(TileType::Wall);
+        // Leave the outer wall alone.
+        let whole_map = Rect::with_size(1, 1, width - 2, height - 2);
+        mb.rooms = self.partition(rand, &mut mb, whole_map);
+
+        mb.player_start = mb.rooms[0].center();
+        mb.amulet_start = mb.find_most_distance();
+        mb.monster_spawns = mb.spawn_monster(&mb.player_start, rand, monsters);
+
+        mb
+    }
+}
+
+impl BspArchitect {
+    /// Carves the rooms for `area` and returns them, joined up.
+    fn partition(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+        area: Rect,
+    ) -> Vec<Rect> {
+        let (width, height) = (area.width(), area.height());
+        let can_split_x = width >= MIN_PARTITION * 2;
+        let can_split_y = height >= MIN_PARTITION * 2;
+
+        // Split across the longer side, so pieces stay roughly square.
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => return vec![self.carve_room(rand, mb, area)],
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) if width > height => true,
+            (true, true) if height > width => false,
+            (true, true) => rand.range(0, 2) == 0,
+        };
+
+        let (first, second) = if split_x {
+            let at = area.x1 + rand.range(MIN_PARTITION, width - MIN_PARTITION + 1);
+            (
+                Rect::with_exact(area.x1, area.y1, at, area.y2),
+                Rect::with_exact(at, area.y1, area.x2, area.y2),
+            )
+        } else {
+            let at = area.y1 + rand.range(MIN_PARTITION, height - MIN_PARTITION + 1);
+            (
+                Rect::with_exact(area.x1, area.y1, area.x2, at),
+                Rect::with_exact(area.x1, at, area.x2, area.y2),
+            )
+        };
+
+        let mut rooms = self.partition(rand, mb, first);
+        let second_rooms = self.partition(rand, mb, second);
+
+        let from = rooms[rand.random_slice_index(&rooms).unwrap()].center();
+        let to = second_rooms[rand.random_slice_index(&second_rooms).unwrap()].center();
+        if rand.range(0, 2) == 1 {
+            mb.apply_horizontal_tunnel(from.x, to.x, from.y);
+            mb.apply_vertical_tunnel(from.y, to.y, to.x);
+        } else {
+            mb.apply_vertical_tunnel(from.y, to.y, from.x);
+            mb.apply_horizontal_tunnel(from.x, to.x, to.y);
+        }
+
+        rooms.extend(second_rooms);
+        rooms
+    }
+
+    /// Puts a room somewhere in `area`, clear of its right and bottom edges
+    /// so it never touches a room in the neighbouring area.
+    fn carve_room(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+        area: Rect,
+    ) -> Rect {
+        let width = rand.range(MIN_ROOM, area.width());
+        let height = rand.range(MIN_ROOM, area.height());
+        let room = Rect::with_size(
+            area.x1 + rand.range(0, area.width() - width),
+            area.y1 + rand.range(0, area.height() - height),
+            width,
+            height,
+        );
+
+        room.for_each(|point| {
+            let idx = mb.map.point2d_to_index(point);
+            mb.map.tiles[idx] = TileType::Floor;
+        });
+        room
+    }
+}
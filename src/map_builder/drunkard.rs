@@ -2,21 +2,27 @@ use super::MapArchitect;
 use crate::prelude::*;
 
 const STAGGER_DISTANCE: usize = 400;
-const NUM_TILES: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
-const DESIRED_FLOOR: usize = NUM_TILES / 3;
 pub struct DrunkArchitect {}
 
 impl MapArchitect for DrunkArchitect {
-    fn new(&mut self, rand: &mut RandomNumberGenerator) -> MapBuilder {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
         let mut mb = MapBuilder {
-            map: Map::new(),
+            map: Map::new(width, height),
             monster_spawns: Vec::new(),
+            vaults: Vec::new(),
             player_start: Point::zero(),
             amulet_start: Point::zero(),
             rooms: Vec::new(),
             themes: super::themes::DungeonTheme::new(),
         };
-        let center = Point::new(SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2);
+        let center = Point::new(width / 2, height / 2);
+        let desired_floor = mb.map.tiles.len() / 3;
 
         mb.fill(TileType::Wall);
         self.drunkard(&center, rand, &mut mb.map);
@@ -27,31 +33,31 @@ impl MapArchitect for DrunkArchitect {
             .iter()
             .filter(|tile| **tile == TileType::Floor)
             .count()
-            < DESIRED_FLOOR
+            < desired_floor
         {
             self.drunkard(
-                &Point::new(rand.range(0, SCREEN_WIDTH), rand.range(0, SCREEN_HEIGHT)),
+                &Point::new(rand.range(0, width), rand.range(0, height)),
                 rand,
                 &mut mb.map,
             );
 
             let dijkstra_map = DijkstraMap::new(
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-                &vec![mb.map.point2d_to_index(center)],
+                width,
+                height,
+                &[mb.map.point2d_to_index(center)],
                 &mb.map,
-                1024.0,
+                mb.map.max_path_depth(),
             );
 
             dijkstra_map
                 .map
                 .iter()
                 .enumerate()
-                .filter(|(_, dist)| *dist > &2000.0)
+                .filter(|(_, dist)| **dist == f32::MAX)
                 .for_each(|(idx, _)| mb.map.tiles[idx] = TileType::Wall);
         }
 
-        mb.monster_spawns = mb.spawn_monster(&center, rand);
+        mb.monster_spawns = mb.spawn_monster(&center, rand, monsters);
         mb.player_start = center;
         mb.amulet_start = mb.find_most_distance();
 
@@ -61,7 +67,7 @@ impl MapArchitect for DrunkArchitect {
 
 impl DrunkArchitect {
     fn drunkard(&mut self, start: &Point, rng: &mut RandomNumberGenerator, map: &mut Map) {
-        let mut drunkard_pos = start.clone();
+        let mut drunkard_pos = *start;
         let mut distance_staggered = 0;
         loop {
             let drunk_idx = map.point2d_to_index(drunkard_pos);
@@ -4,23 +4,28 @@ use crate::prelude::*;
 pub struct EmptyArchitect {}
 
 impl MapArchitect for EmptyArchitect {
-    fn new(&mut self, rand: &mut RandomNumberGenerator) -> MapBuilder {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
         let mut mb = MapBuilder {
-            map: Map::new(),
+            map: Map::new(width, height),
             rooms: Vec::new(),
             monster_spawns: Vec::new(),
+            vaults: Vec::new(),
             player_start: Point::zero(),
             amulet_start: Point::zero(),
             themes: super::themes::DungeonTheme::new(),
         };
         mb.fill(TileType::Floor);
-        mb.player_start = Point::new(SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2);
+        mb.player_start = Point::new(width / 2, height / 2);
         mb.amulet_start = mb.find_most_distance();
-        for _ in 0..=50 {
-            mb.monster_spawns.push(Point::new(
-                rand.range(1, SCREEN_WIDTH),
-                rand.range(1, SCREEN_WIDTH),
-            ));
+        for _ in 0..monsters {
+            mb.monster_spawns
+                .push(Point::new(rand.range(1, width), rand.range(1, height)));
         }
 
         mb
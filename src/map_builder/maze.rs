@@ -0,0 +1,151 @@
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// Up, right, down and left, in maze cells.
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Carves a maze with a recursive backtracker: the walk moves to a random
+/// cell it hasn't been to yet, and backs up when it runs out. Cells sit on
+/// odd tiles with walls between them, so every corridor is one tile wide.
+/// The player starts in one corner and the amulet waits at the far end.
+pub struct MazeArchitect {
+    /// Chance out of 100 that a dead end has its far wall knocked through.
+    /// 0 leaves a perfect maze with a single path between any two places;
+    /// 100 leaves no dead ends at all.
+    pub braid: i32,
+    /// Open rooms carved into the maze once it is built.
+    pub chambers: usize,
+}
+
+impl MapArchitect for MazeArchitect {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
+        let mut mb = MapBuilder {
+            map: Map::new(width, height),
+            rooms: Vec::new(),
+            monster_spawns: Vec::new(),
+            vaults: Vec::new(),
+            player_start: Point::zero(),
+            amulet_start: Point::zero(),
+            themes: super::themes::DungeonTheme::new(),
+        };
+
+        mb.fill(TileType::Wall);
+        let cells = Point::new((width - 1) / 2, (height - 1) / 2);
+        self.carve_maze(rand, &mut mb.map, cells);
+        self.braid(rand, &mut mb.map, cells);
+        self.carve_chambers(rand, &mut mb, cells);
+
+        mb.player_start = Point::new(1, 1);
+        mb.amulet_start = mb.find_most_distance();
+        mb.monster_spawns = mb.spawn_monster(&mb.player_start, rand, monsters);
+
+        mb
+    }
+}
+
+impl MazeArchitect {
+    fn carve_maze(&mut self, rand: &mut RandomNumberGenerator, map: &mut Map, cells: Point) {
+        let mut visited = vec![false; (cells.x * cells.y) as usize];
+        let mut stack = vec![Point::zero()];
+        visited[0] = true;
+        carve(map, Point::zero());
+
+        while let Some(cell) = stack.last().copied() {
+            let unvisited: Vec<Point> = neighbours(cell, cells)
+                .filter(|next| !visited[(next.y * cells.x + next.x) as usize])
+                .collect();
+
+            match rand.random_slice_entry(&unvisited) {
+                Some(next) => {
+                    visited[(next.y * cells.x + next.x) as usize] = true;
+                    carve_between(map, cell, *next);
+                    carve(map, *next);
+                    stack.push(*next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    /// Gives some dead ends a second way out, turning the maze's tree of
+    /// corridors into loops.
+    fn braid(&mut self, rand: &mut RandomNumberGenerator, map: &mut Map, cells: Point) {
+        for y in 0..cells.y {
+            for x in 0..cells.x {
+                let cell = Point::new(x, y);
+                let (open, closed): (Vec<Point>, Vec<Point>) =
+                    neighbours(cell, cells).partition(|next| {
+                        map.tiles[map.point2d_to_index(tile_between(cell, *next))]
+                            == TileType::Floor
+                    });
+                if open.len() != 1 || rand.range(0, 100) >= self.braid {
+                    continue;
+                }
+                if let Some(next) = rand.random_slice_entry(&closed) {
+                    carve_between(map, cell, *next);
+                }
+            }
+        }
+    }
+
+    fn carve_chambers(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+        cells: Point,
+    ) {
+        for _ in 0..self.chambers {
+            // Chambers span whole cells, so their edges line up with the corridors.
+            let (cell_width, cell_height) = (rand.range(2, 5), rand.range(2, 4));
+            if cell_width >= cells.x || cell_height >= cells.y {
+                continue;
+            }
+            let corner = Point::new(
+                rand.range(0, cells.x - cell_width),
+                rand.range(0, cells.y - cell_height),
+            );
+            let room = Rect::with_size(
+                corner.x * 2 + 1,
+                corner.y * 2 + 1,
+                cell_width * 2 - 1,
+                cell_height * 2 - 1,
+            );
+            room.for_each(|point| {
+                let idx = mb.map.point2d_to_index(point);
+                mb.map.tiles[idx] = TileType::Floor;
+            });
+            mb.rooms.push(room);
+        }
+    }
+}
+
+/// The cells next to `cell` in a maze `cells` across.
+fn neighbours(cell: Point, cells: Point) -> impl Iterator<Item = Point> {
+    DIRECTIONS
+        .iter()
+        .map(move |(dx, dy)| cell + Point::new(*dx, *dy))
+        .filter(move |next| next.x >= 0 && next.y >= 0 && next.x < cells.x && next.y < cells.y)
+}
+
+fn carve(map: &mut Map, cell: Point) {
+    let idx = map.point2d_to_index(Point::new(cell.x * 2 + 1, cell.y * 2 + 1));
+    map.tiles[idx] = TileType::Floor;
+}
+
+fn carve_between(map: &mut Map, cell: Point, next: Point) {
+    let idx = map.point2d_to_index(tile_between(cell, next));
+    map.tiles[idx] = TileType::Floor;
+}
+
+/// The wall tile separating two neighbouring cells.
+fn tile_between(cell: Point, next: Point) -> Point {
+    Point::new(cell.x + next.x + 1, cell.y + next.y + 1)
+}
@@ -1,31 +1,63 @@
 #![allow(dead_code, unused_variables)]
-use crate::{
-    map_builder::{
-        automata::CellularAutomataArchitect,
-        drunkard::DrunkArchitect,
-        prefab::apply_prefab,
-        rooms::RoomsArchitect,
-        themes::{DungeonTheme, ForestTheme, CaveTheme, DesertTheme, IceTheme, CryptTheme, SwampTheme, LavaTheme},
-    },
-    prelude::*,
-};
+use crate::prelude::*;
 mod automata;
+mod bsp;
 mod drunkard;
 mod empty;
+mod maze;
+mod modifiers;
+mod pipeline;
 mod prefab;
+mod registry;
 mod rooms;
 mod themes;
-use empty::EmptyArchitect;
+mod voronoi;
+mod wfc;
+pub use modifiers::{
+    ApplyTheme, CullUnreachable, Overlay, PlaceDoors, PlaceSpawns, PlaceStairs, Pools,
+    WidenCorridors,
+};
+pub use pipeline::{MapModifier, MapPipeline};
+pub use prefab::{PlacedVault, PrefabVault, VaultSpawn};
+pub use registry::{
+    ArchitectEntry, ArchitectFactory, ArchitectRegistry, MAX_TOTAL_WEIGHT, pick_weighted,
+};
+pub use themes::{THEME_NAMES, theme_by_name};
 
-trait MapArchitect {
-    fn new(&mut self, rand: &mut RandomNumberGenerator) -> MapBuilder;
+/// Lays out a level's walls and floors, and where the player, the level's
+/// goal and its monsters start. Architects are looked up by name in an
+/// `ArchitectRegistry`.
+#[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+pub trait MapArchitect {
+    /// Builds a `width` by `height` level with at most `monsters` spawn points.
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder;
 }
 
 pub trait MapThemes: Send + Sync {
-    fn tile_to_render(&self, tile_type: TileType) -> FontCharType;
+    /// Stable identifier used to store the theme in save files.
+    fn name(&self) -> &'static str;
+    /// The glyph `tile_type` is drawn with. Themes override the tiles they
+    /// give a look of their own, and leave the rest to `default_glyph`.
+    fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
+        themes::default_glyph(tile_type)
+    }
 }
 
+/// Rooms dug into a level of the default size. Bigger levels get more, in
+/// proportion to their area.
 pub const NUM_ROOMS: usize = 20;
+/// Rooms on a level of the default size are less than this across and
+/// down. Rooms on bigger levels can be bigger too, though not in proportion,
+/// so they stay rooms rather than halls.
+const MAX_ROOM_SIZE: i32 = 10;
+/// Levels built for a depth before giving up on its architect.
+const BUILD_ATTEMPTS: usize = 20;
 
 pub struct MapBuilder {
     pub map: Map,
@@ -33,32 +65,118 @@ pub struct MapBuilder {
     pub player_start: Point,
     pub amulet_start: Point,
     pub monster_spawns: Vec<Point>,
+    /// The prefab vaults stamped into the level, and what they hold.
+    pub vaults: Vec<PlacedVault>,
     pub themes: Box<dyn MapThemes>,
 }
 
 impl MapBuilder {
-    pub fn new(rand: &mut RandomNumberGenerator) -> Self {
-        let mut architect: Box<dyn MapArchitect> = match rand.range(0, 3) {
-            0 => Box::new(DrunkArchitect {}),
-            1 => Box::new(RoomsArchitect {}),
-            _ => Box::new(CellularAutomataArchitect {}),
-        };
+    /// Builds the level at `depth` in the configured size, with the
+    /// configured architect and theme, or ones picked from the level's plan
+    /// where the config leaves them open. A plan that lists no architects
+    /// leaves the pick to `architects`.
+    pub fn new(
+        rand: &mut RandomNumberGenerator,
+        config: &GameConfig,
+        architects: &ArchitectRegistry,
+        vaults: &VaultLibrary,
+        level: &LevelPlan,
+        depth: u32,
+    ) -> Self {
+        let entry = Self::pick_architect(rand, config, architects, level, depth);
+        Self::build(rand, config, entry, vaults, level, depth)
+    }
 
-        let mut mb = architect.new(rand);
-        apply_prefab(&mut mb, rand);
-
-        mb.themes = match rand.range(0, 8) {
-            0 => DungeonTheme::new(),
-            1 => ForestTheme::new(),
-            2 => CaveTheme::new(),
-            3 => DesertTheme::new(),
-            4 => IceTheme::new(),
-            5 => CryptTheme::new(),
-            6 => SwampTheme::new(),
-            _ => LavaTheme::new(),
+    /// The architect the level at `depth` is built with: the configured one,
+    /// or one picked from the level's plan or from `architects`.
+    pub fn pick_architect<'a>(
+        rand: &mut RandomNumberGenerator,
+        config: &GameConfig,
+        architects: &'a ArchitectRegistry,
+        level: &LevelPlan,
+        depth: u32,
+    ) -> &'a ArchitectEntry {
+        // Names are checked when the command line and the plan are read, and
+        // plans that leave the pick open are checked to have something to pick.
+        match &config.architect {
+            Some(name) => architects.get(name),
+            None if level.architects.is_empty() => architects.pick(rand, depth),
+            None => level
+                .pick_architect(rand)
+                .and_then(|name| architects.get(name)),
+        }
+        .unwrap()
+    }
+
+    /// Builds the level at `depth` with `entry`'s architect. Every
+    /// architect's level gets as many prefab vaults from `vaults` as the plan
+    /// asks for and there is room for, has what the player can't reach
+    /// walled in, and gets its theme and stairs to match the plan's goal. A
+    /// level whose goal can't be reached is built again, without vaults once
+    /// `BUILD_ATTEMPTS` of those have failed, and as an open floor if even
+    /// that keeps failing.
+    pub fn build(
+        rand: &mut RandomNumberGenerator,
+        config: &GameConfig,
+        entry: &ArchitectEntry,
+        vaults: &VaultLibrary,
+        level: &LevelPlan,
+        depth: u32,
+    ) -> Self {
+        for count in [level.vaults, 0] {
+            for _ in 0..BUILD_ATTEMPTS {
+                let prefabs = PrefabVault::new(vaults.for_depth(depth), count, level.vault_goal);
+                let mb = Self::build_with(rand, config, (entry.build)(), prefabs, level, depth);
+                if mb.goal_reachable() {
+                    return mb;
+                }
+            }
+        }
+        // Every tile of an open floor joins up, so its goal can always be reached.
+        let open_floor = Box::new(empty::EmptyArchitect {});
+        let prefabs = PrefabVault::new(Vec::new(), 0, false);
+        Self::build_with(rand, config, open_floor, prefabs, level, depth)
+    }
+
+    fn build_with(
+        rand: &mut RandomNumberGenerator,
+        config: &GameConfig,
+        architect: Box<dyn MapArchitect>,
+        prefabs: PrefabVault,
+        level: &LevelPlan,
+        depth: u32,
+    ) -> Self {
+        let theme = match &config.theme {
+            Some(name) => ApplyTheme::Named(name.clone()),
+            None => ApplyTheme::AnyOf(level.themes.clone()),
         };
+        MapPipeline::new(architect)
+            .with(prefabs)
+            .with(CullUnreachable)
+            .with(theme)
+            .with(PlaceStairs {
+                down: level.goal == LevelGoal::Stairs,
+                up: depth > 0,
+            })
+            .new(rand, config.map_width, config.map_height, level.monsters)
+    }
 
-        mb
+    /// Whether the player can walk from their start to the goal.
+    fn goal_reachable(&self) -> bool {
+        if !self.map.can_enter_tile(self.player_start)
+            || !self.map.can_enter_tile(self.amulet_start)
+            || self.player_start == self.amulet_start
+        {
+            return false;
+        }
+        let dijkstra_map = DijkstraMap::new(
+            self.map.width,
+            self.map.height,
+            &[self.map.point2d_to_index(self.player_start)],
+            &self.map,
+            self.map.max_path_depth(),
+        );
+        dijkstra_map.map[self.map.point2d_to_index(self.amulet_start)] < f32::MAX
     }
 
     fn fill(&mut self, tile: TileType) {
@@ -67,11 +185,11 @@ impl MapBuilder {
 
     fn find_most_distance(&self) -> Point {
         let dijkistra_map = DijkstraMap::new(
-            SCREEN_WIDTH,
-            SCREEN_HEIGHT,
-            &vec![self.map.point2d_to_index(self.player_start)],
+            self.map.width,
+            self.map.height,
+            &[self.map.point2d_to_index(self.player_start)],
             &self.map,
-            1024.0,
+            self.map.max_path_depth(),
         );
         const UNREACHABLE: &f32 = &f32::MAX;
 
@@ -88,13 +206,19 @@ impl MapBuilder {
     }
 
     fn build_random_rooms(&mut self, rand: &mut RandomNumberGenerator) {
-        while self.rooms.len() < NUM_ROOMS {
+        // How many times bigger than the default the level is, and never less.
+        let scale = (self.map.width * self.map.height) as f32
+            / (DEFAULT_MAP_WIDTH * DEFAULT_MAP_HEIGHT) as f32;
+        let scale = scale.max(1.0);
+        let num_rooms = (NUM_ROOMS as f32 * scale).round() as usize;
+        let max_size = (MAX_ROOM_SIZE as f32 * scale.powf(0.25)).round() as i32;
+        while self.rooms.len() < num_rooms {
             // Generate random rooms.
             let rooms = Rect::with_size(
-                rand.range(1, SCREEN_WIDTH - 10),
-                rand.range(1, SCREEN_HEIGHT - 10),
-                rand.range(2, 10),
-                rand.range(2, 10),
+                rand.range(1, self.map.width - max_size),
+                rand.range(1, self.map.height - max_size),
+                rand.range(2, max_size),
+                rand.range(2, max_size),
             );
 
             let mut overlap = false;
@@ -107,8 +231,12 @@ impl MapBuilder {
             if !overlap {
                 rooms.for_each(|room| {
                     // check whether the rooms are withing the map boundaries.
-                    if room.x > 0 && room.x < SCREEN_WIDTH && room.y > 0 && room.y < SCREEN_HEIGHT {
-                        let idx = map_idx(room.x, room.y);
+                    if room.x > 0
+                        && room.x < self.map.width
+                        && room.y > 0
+                        && room.y < self.map.height
+                    {
+                        let idx = self.map.map_idx(room.x, room.y);
                         self.map.tiles[idx] = TileType::Floor;
                     }
                 });
@@ -121,7 +249,7 @@ impl MapBuilder {
         use std::cmp::{max, min};
         for y in min(y1, y2)..=max(y1, y2) {
             if let Some(idx) = self.map.try_idx(Point::new(x, y)) {
-                self.map.tiles[idx as usize] = TileType::Floor;
+                self.map.tiles[idx] = TileType::Floor;
             }
         }
     }
@@ -130,7 +258,7 @@ impl MapBuilder {
         use std::cmp::{max, min};
         for x in min(x1, x2)..=max(x1, x2) {
             if let Some(idx) = self.map.try_idx(Point::new(x, y)) {
-                self.map.tiles[idx as usize] = TileType::Floor
+                self.map.tiles[idx] = TileType::Floor
             }
         }
     }
@@ -138,7 +266,7 @@ impl MapBuilder {
     fn build_corridors(&mut self, rand: &mut RandomNumberGenerator) {
         let mut rooms = self.rooms.clone();
         // Sorting rooms by their center points before allocating corridors. This helps to avoid long corridors that almost certainly overlap with other rooms.
-        rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
+        rooms.sort_by_key(|a| a.center().x);
 
         for (idx, room) in rooms.iter().enumerate().skip(1) {
             let prev = rooms[idx - 1].center();
@@ -154,8 +282,12 @@ impl MapBuilder {
         }
     }
 
-    fn spawn_monster(&self, start: &Point, rng: &mut RandomNumberGenerator) -> Vec<Point> {
-        const NUM_MONSTERS: usize = 50;
+    fn spawn_monster(
+        &self,
+        start: &Point,
+        rng: &mut RandomNumberGenerator,
+        count: usize,
+    ) -> Vec<Point> {
         let mut spawnable_tiles = self
             .map
             .tiles
@@ -170,9 +302,9 @@ impl MapBuilder {
             .collect::<Vec<Point>>();
 
         let mut spawns = Vec::new();
-        for _ in 0..NUM_MONSTERS {
+        for _ in 0..count.min(spawnable_tiles.len()) {
             let target_idx = rng.random_slice_index(&spawnable_tiles).unwrap();
-            spawns.push(spawnable_tiles[target_idx].clone());
+            spawns.push(spawnable_tiles[target_idx]);
             spawnable_tiles.remove(target_idx);
         }
 
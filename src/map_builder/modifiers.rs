@@ -0,0 +1,330 @@
+use super::{MapArchitect, MapModifier};
+use crate::prelude::*;
+
+/// Lays the floor of a second architect's level over this one, and adds its
+/// rooms; where the player, goal and spawns are is left alone. Follow it with
+/// `CullUnreachable` when the two levels might not meet.
+pub struct Overlay {
+    architect: Box<dyn MapArchitect>,
+}
+
+impl Overlay {
+    pub fn new(architect: Box<dyn MapArchitect>) -> Self {
+        Self { architect }
+    }
+}
+
+impl MapModifier for Overlay {
+    fn apply(&mut self, rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, monsters: usize) {
+        let overlay = self
+            .architect
+            .new(rand, mb.map.width, mb.map.height, monsters);
+        mb.map
+            .tiles
+            .iter_mut()
+            .zip(overlay.map.tiles)
+            .filter(|(_, over)| *over == TileType::Floor)
+            .for_each(|(tile, _)| *tile = TileType::Floor);
+        mb.rooms.extend(overlay.rooms);
+    }
+}
+
+/// Walls in everything the player can't walk to from their start, and drops
+/// the spawn points that were there. The goal is left where it is, so check
+/// it can still be reached.
+pub struct CullUnreachable;
+
+impl MapModifier for CullUnreachable {
+    fn apply(&mut self, _rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        let dijkstra_map = DijkstraMap::new(
+            mb.map.width,
+            mb.map.height,
+            &[mb.map.point2d_to_index(mb.player_start)],
+            &mb.map,
+            mb.map.max_path_depth(),
+        );
+        dijkstra_map
+            .map
+            .iter()
+            .enumerate()
+            .filter(|(_, dist)| **dist == f32::MAX)
+            .for_each(|(idx, _)| mb.map.tiles[idx] = TileType::Wall);
+
+        let map = &mb.map;
+        mb.monster_spawns.retain(|pos| map.can_enter_tile(*pos));
+    }
+}
+
+/// Doubles the width of one-tile corridors, so fights in them aren't always
+/// one at a time. The walls round rooms are left alone, so doorways stay one
+/// tile wide.
+pub struct WidenCorridors;
+
+impl MapModifier for WidenCorridors {
+    fn apply(&mut self, _rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        let rooms = dug_rooms(mb);
+        let touches_room = |x: i32, y: i32| {
+            rooms
+                .iter()
+                .any(|room| x >= room.x1 - 1 && x <= room.x2 && y >= room.y1 - 1 && y <= room.y2)
+        };
+        let map = &mut mb.map;
+        let tiles = map.tiles.clone();
+        let wall = |x: i32, y: i32| tiles[map.map_idx(x, y)] == TileType::Wall;
+
+        let mut widened = Vec::new();
+        for y in 1..map.height - 1 {
+            for x in 1..map.width - 1 {
+                if tiles[map.map_idx(x, y)] != TileType::Floor {
+                    continue;
+                }
+                // Stay inside the outer wall.
+                if wall(x - 1, y)
+                    && wall(x + 1, y)
+                    && x + 1 < map.width - 1
+                    && !touches_room(x + 1, y)
+                {
+                    widened.push(map.map_idx(x + 1, y));
+                }
+                if wall(x, y - 1)
+                    && wall(x, y + 1)
+                    && y + 1 < map.height - 1
+                    && !touches_room(x, y + 1)
+                {
+                    widened.push(map.map_idx(x, y + 1));
+                }
+            }
+        }
+        widened
+            .into_iter()
+            .for_each(|idx| map.tiles[idx] = TileType::Floor);
+    }
+}
+
+/// Hangs a door in each corridor where it meets a room, leaving the player's
+/// start, the spawn points, and the goal and the tiles beside it clear.
+pub struct PlaceDoors;
+
+impl MapModifier for PlaceDoors {
+    fn apply(&mut self, _rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        let map = &mb.map;
+        let mut doors: Vec<Point> = Vec::new();
+        for room in dug_rooms(mb) {
+            // The ring of tiles just outside the room, each with the room
+            // tile it opens onto.
+            let sides = (room.y1..room.y2)
+                .flat_map(|y| {
+                    [
+                        (Point::new(room.x1 - 1, y), Point::new(1, 0)),
+                        (Point::new(room.x2, y), Point::new(-1, 0)),
+                    ]
+                })
+                .chain((room.x1..room.x2).flat_map(|x| {
+                    [
+                        (Point::new(x, room.y1 - 1), Point::new(0, 1)),
+                        (Point::new(x, room.y2), Point::new(0, -1)),
+                    ]
+                }));
+            for (pos, inward) in sides {
+                let tile = |pos: Point| map.try_idx(pos).map(|idx| map.tiles[idx]);
+                let wall = |pos: Point| tile(pos) != Some(TileType::Floor);
+                // A one-tile gap in the wall, across the way into the room.
+                let across = Point::new(inward.y, inward.x);
+                let junction = tile(pos) == Some(TileType::Floor)
+                    && tile(pos + inward) == Some(TileType::Floor)
+                    && wall(pos + across)
+                    && wall(pos - across);
+                let clear = pos != mb.player_start
+                    && DistanceAlg::Manhattan.distance2d(pos, mb.amulet_start) > 1.0
+                    && !mb.monster_spawns.contains(&pos)
+                    && !doors
+                        .iter()
+                        .any(|door| DistanceAlg::Manhattan.distance2d(*door, pos) <= 1.0);
+                if junction && clear {
+                    doors.push(pos);
+                }
+            }
+        }
+        for pos in doors {
+            let idx = mb.map.point2d_to_index(pos);
+            mb.map.tiles[idx] = TileType::Door;
+        }
+    }
+}
+
+/// The level's rooms that were dug out. Rooms an architect gave up on for
+/// overlapping others never were.
+fn dug_rooms(mb: &MapBuilder) -> Vec<Rect> {
+    let map = &mb.map;
+    mb.rooms
+        .iter()
+        .filter(|room| {
+            room.point_set().iter().all(|pos| {
+                map.try_idx(*pos)
+                    .is_some_and(|idx| map.tiles[idx] == TileType::Floor)
+            })
+        })
+        .copied()
+        .collect()
+}
+
+/// Pools leave this much room around where the player starts.
+const POOL_CLEARANCE: f32 = 4.0;
+
+/// Floods `count` blobs of open floor with one tile, ringed with another: deep
+/// water with shallows, lava with rubble, or just patches of grass. Pools keep
+/// clear of walls, so there is always a way round them, and of the player's
+/// start, the spawn points, and the goal and the tiles beside it.
+pub struct Pools {
+    core: TileType,
+    rim: Option<TileType>,
+    count: usize,
+}
+
+impl Pools {
+    pub fn new(core: TileType, rim: Option<TileType>, count: usize) -> Self {
+        Self { core, rim, count }
+    }
+}
+
+impl MapModifier for Pools {
+    fn apply(&mut self, rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        let neighbours = |pos: Point| {
+            (-1..=1)
+                .flat_map(move |dy| (-1..=1).map(move |dx| pos + Point::new(dx, dy)))
+                .filter(move |next| *next != pos)
+        };
+
+        for _ in 0..self.count {
+            let map = &mb.map;
+            let floor = |pos: Point| {
+                map.try_idx(pos)
+                    .is_some_and(|idx| map.tiles[idx] == TileType::Floor)
+                    && DistanceAlg::Pythagoras.distance2d(pos, mb.player_start) > POOL_CLEARANCE
+                    && DistanceAlg::Manhattan.distance2d(pos, mb.amulet_start) > 1.0
+                    && !mb.monster_spawns.contains(&pos)
+            };
+            let open: Vec<Point> = (0..map.tiles.len())
+                .map(|idx| map.index_to_point2d(idx))
+                .filter(|pos| {
+                    floor(*pos)
+                        && neighbours(*pos).all(|next| {
+                            map.try_idx(next)
+                                .is_some_and(|idx| map.tiles[idx] != TileType::Wall)
+                        })
+                })
+                .collect();
+            let Some(centre) = rand.random_slice_entry(&open).copied() else {
+                return;
+            };
+            let radius = rand.range(2, 5) as f32;
+            let core: Vec<Point> = open
+                .into_iter()
+                .filter(|pos| DistanceAlg::Pythagoras.distance2d(centre, *pos) <= radius)
+                .collect();
+            let rim: Vec<Point> = core
+                .iter()
+                .flat_map(|pos| neighbours(*pos))
+                .filter(|next| floor(*next) && !core.contains(next))
+                .collect();
+
+            for pos in core {
+                let idx = mb.map.point2d_to_index(pos);
+                mb.map.tiles[idx] = self.core;
+            }
+            if let Some(tile) = self.rim {
+                for pos in rim {
+                    let idx = mb.map.point2d_to_index(pos);
+                    mb.map.tiles[idx] = tile;
+                }
+            }
+        }
+    }
+}
+
+/// Puts stairs down on the goal, and stairs back up on the player's start.
+pub struct PlaceStairs {
+    pub down: bool,
+    pub up: bool,
+}
+
+impl MapModifier for PlaceStairs {
+    fn apply(&mut self, _rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        if self.down {
+            let idx = mb.map.point2d_to_index(mb.amulet_start);
+            mb.map.tiles[idx] = TileType::Exit;
+        }
+        if self.up {
+            let idx = mb.map.point2d_to_index(mb.player_start);
+            mb.map.tiles[idx] = TileType::UpStairs;
+        }
+    }
+}
+
+/// Picks the level's spawn points again, for when earlier steps have
+/// reshaped the level its architect chose them for.
+pub struct PlaceSpawns;
+
+impl MapModifier for PlaceSpawns {
+    fn apply(&mut self, rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, monsters: usize) {
+        mb.monster_spawns = mb.spawn_monster(&mb.player_start, rand, monsters);
+    }
+}
+
+/// Sets the theme the level is drawn with. Theme names are checked when the
+/// command line and the plan are read.
+pub enum ApplyTheme {
+    Named(String),
+    /// One of these, picked at random.
+    AnyOf(Vec<String>),
+}
+
+impl MapModifier for ApplyTheme {
+    fn apply(&mut self, rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        let name = match self {
+            ApplyTheme::Named(name) => name,
+            ApplyTheme::AnyOf(names) => &names[rand.range(0, names.len() as i32) as usize],
+        };
+        mb.themes = theme_by_name(name).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two rooms with a wall between them: the player starts in the left one,
+    /// and the right one can't be reached.
+    fn sealed_rooms() -> MapBuilder {
+        let mut mb = MapBuilder {
+            map: Map::new(20, 10),
+            rooms: Vec::new(),
+            monster_spawns: vec![Point::new(3, 3), Point::new(15, 5)],
+            vaults: Vec::new(),
+            player_start: Point::new(2, 2),
+            amulet_start: Point::new(4, 4),
+            themes: super::super::themes::DungeonTheme::new(),
+        };
+        mb.fill(TileType::Wall);
+        for (x1, x2) in [(1, 8), (11, 18)] {
+            for y in 1..9 {
+                for x in x1..=x2 {
+                    let idx = mb.map.map_idx(x, y);
+                    mb.map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+        mb
+    }
+
+    #[test]
+    fn cull_unreachable_walls_in_sealed_rooms_and_drops_their_spawns() {
+        let mut mb = sealed_rooms();
+        let mut rand = RandomNumberGenerator::seeded(1);
+        CullUnreachable.apply(&mut rand, &mut mb, 0);
+
+        assert_eq!(mb.monster_spawns, vec![Point::new(3, 3)]);
+        assert!((11..=18).all(|x| !mb.map.can_enter_tile(Point::new(x, 5))));
+        assert!(mb.map.can_enter_tile(Point::new(8, 8)));
+    }
+}
@@ -0,0 +1,55 @@
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// A step run on a level after its architect has laid it out: anything from
+/// carving extra floor to deciding where the stairs go.
+pub trait MapModifier {
+    /// Changes `mb` in place. `monsters` is the most spawn points the level
+    /// should end up with, as given to its architect.
+    fn apply(&mut self, rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, monsters: usize);
+}
+
+/// A base architect followed by modifiers, run in the order they were added.
+/// A pipeline is an architect itself, so one can be registered by name, or
+/// be the base of another:
+///
+/// ```ignore
+/// MapPipeline::new(Box::new(DrunkArchitect {}))
+///     .with(Overlay::new(Box::new(BspArchitect {})))
+///     .with(CullUnreachable)
+///     .with(PlaceSpawns)
+/// ```
+pub struct MapPipeline {
+    base: Box<dyn MapArchitect>,
+    modifiers: Vec<Box<dyn MapModifier>>,
+}
+
+impl MapPipeline {
+    pub fn new(base: Box<dyn MapArchitect>) -> Self {
+        Self {
+            base,
+            modifiers: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, modifier: impl MapModifier + 'static) -> Self {
+        self.modifiers.push(Box::new(modifier));
+        self
+    }
+}
+
+impl MapArchitect for MapPipeline {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
+        let mut mb = self.base.new(rand, width, height, monsters);
+        for modifier in &mut self.modifiers {
+            modifier.apply(rand, &mut mb, monsters);
+        }
+        mb
+    }
+}
@@ -1,86 +1,179 @@
+use serde::Serialize;
+
+use super::MapModifier;
 use crate::prelude::*;
-const FORTRESS: (&str, i32, i32) = (
-    "------------
-    ---######--- 
-    ---#----#--- 
-    ---#-M--#---
-    -###----###-
-    --M------M--
-    -###----###- 
-    ---#----#---
-    ---#----#---
-    ---######---
-    ------------",
-    12,
-    11,
-);
 
-pub fn apply_prefab(mb: &mut MapBuilder, rng: &mut RandomNumberGenerator) {
-    let mut placement = None;
-    let dijkstra_map = DijkstraMap::new(
-        SCREEN_WIDTH,
-        SCREEN_HEIGHT,
-        &vec![mb.map.point2d_to_index(mb.player_start)],
-        &mb.map,
-        1024.0,
-    );
+/// Tries at a spot for each vault before giving up on it.
+const PLACEMENT_ATTEMPTS: usize = 10;
 
-    let mut attempts = 0;
-    while placement.is_none() && attempts < 10 {
-        let dimensions = Rect::with_size(
-            rng.range(0, SCREEN_WIDTH - FORTRESS.1),
-            rng.range(0, SCREEN_HEIGHT - FORTRESS.2),
-            FORTRESS.1,
-            FORTRESS.2,
-        );
+/// Vaults keep at least this many steps away from where the player starts.
+const MIN_PLAYER_DISTANCE: f32 = 20.0;
 
-        let mut can_place = false;
-        dimensions.for_each(|point| {
-            let idx = mb.map.point2d_to_index(point);
-            let distance = dijkstra_map.map[idx];
+/// Something a vault holds, spawned when its level is first entered.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum VaultSpawn {
+    Monster,
+    Item,
+    Template(String),
+}
+
+/// A vault stamped into a level.
+#[derive(Clone, Debug, Serialize)]
+pub struct PlacedVault {
+    pub name: String,
+    pub area: Rect,
+    pub spawns: Vec<(Point, VaultSpawn)>,
+}
 
-            if distance < 2000.0 && distance > 20.0 && mb.amulet_start != point {
-                can_place = true;
+/// Stamps up to `count` vaults, picked from those given, into spots away
+/// from the player that a few tries turn up. With `goal` set, the first of
+/// them with an exit moves the level's goal onto it.
+pub struct PrefabVault {
+    vaults: Vec<Vault>,
+    count: usize,
+    goal: bool,
+}
+
+impl PrefabVault {
+    pub fn new(vaults: Vec<Vault>, count: usize, goal: bool) -> Self {
+        Self {
+            vaults,
+            count,
+            goal,
+        }
+    }
+}
+
+impl MapModifier for PrefabVault {
+    fn apply(&mut self, rand: &mut RandomNumberGenerator, mb: &mut MapBuilder, _monsters: usize) {
+        for _ in 0..self.count {
+            let Some(vault) = rand.random_slice_entry(&self.vaults) else {
+                return;
+            };
+            if rand.range(0, vault.rarity as i32) != 0 {
+                continue;
             }
-        });
+            let rows = orient(rand, vault);
+            if let Some(corner) = find_spot(rand, mb, &rows) {
+                self.goal &= !stamp(mb, vault, &rows, corner, self.goal);
+            }
+        }
+    }
+}
 
-        if can_place {
-            placement = Some(Point::new(dimensions.x1, dimensions.y1));
-            let point = dimensions.point_set();
-            mb.monster_spawns.retain(|pt| !point.contains(pt));
+/// The vault's rows, turned and flipped as far as it allows.
+fn orient(rand: &mut RandomNumberGenerator, vault: &Vault) -> Vec<Vec<char>> {
+    let mut rows: Vec<Vec<char>> = vault.rows.iter().map(|row| row.chars().collect()).collect();
+    if vault.mirror && rand.range(0, 2) == 1 {
+        rows.iter_mut().for_each(|row| row.reverse());
+    }
+    if vault.rotate {
+        for _ in 0..rand.range(0, 4) {
+            // A quarter turn clockwise: the bottom row becomes the left column.
+            rows = (0..rows[0].len())
+                .map(|x| rows.iter().rev().map(|row| row[x]).collect())
+                .collect();
         }
+    }
+    rows
+}
 
-        attempts += 1;
+/// The top left corner of a spot inside the outer wall, clear of the player,
+/// the goal and other vaults, and joined to the rest of the level.
+fn find_spot(
+    rand: &mut RandomNumberGenerator,
+    mb: &MapBuilder,
+    rows: &[Vec<char>],
+) -> Option<Point> {
+    let (width, height) = (rows[0].len() as i32, rows.len() as i32);
+    if width > mb.map.width - 2 || height > mb.map.height - 2 {
+        return None;
     }
+    let dijkstra_map = DijkstraMap::new(
+        mb.map.width,
+        mb.map.height,
+        &[mb.map.point2d_to_index(mb.player_start)],
+        &mb.map,
+        mb.map.max_path_depth(),
+    );
 
-    if let Some(placement) = placement {
-        let string_vec: Vec<char> = FORTRESS
-            .0
-            .chars()
-            .filter(|a| *a != '\r' && *a != '\n')
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let area = Rect::with_size(
+            rand.range(1, mb.map.width - width),
+            rand.range(1, mb.map.height - height),
+            width,
+            height,
+        );
+        let distances: Vec<f32> = area
+            .point_set()
+            .iter()
+            .map(|point| dijkstra_map.map[mb.map.point2d_to_index(*point)])
             .collect();
+        let clear = !area.point_in_rect(mb.player_start)
+            && !area.point_in_rect(mb.amulet_start)
+            && !mb.vaults.iter().any(|vault| vault.area.intersect(&area))
+            && distances
+                .iter()
+                .all(|distance| *distance > MIN_PLAYER_DISTANCE);
+        let joined = distances.iter().any(|distance| *distance < f32::MAX);
+        if clear && joined {
+            return Some(Point::new(area.x1, area.y1));
+        }
+    }
+    None
+}
 
-        let mut i = 0;
+/// Stamps the vault with its top left corner at `corner`, and returns
+/// whether its exit took the level's goal, which it only may with `goal`.
+fn stamp(
+    mb: &mut MapBuilder,
+    vault: &Vault,
+    rows: &[Vec<char>],
+    corner: Point,
+    goal: bool,
+) -> bool {
+    let area = Rect::with_size(corner.x, corner.y, rows[0].len() as i32, rows.len() as i32);
+    mb.monster_spawns
+        .retain(|point| !area.point_in_rect(*point));
 
-        for ty in placement.y..placement.y + FORTRESS.2 {
-            for tx in placement.x..placement.x + FORTRESS.1 {
-                let idx = map_idx(tx, ty);
-                let c = string_vec[i];
-                match c {
-                    'M' => {
-                        mb.map.tiles[idx] = TileType::Floor;
-                        mb.monster_spawns.push(Point::new(tx, ty));
-                    }
-                    '-' => {
-                        mb.map.tiles[idx] = TileType::Floor;
-                    }
-                    '#' => {
-                        mb.map.tiles[idx] = TileType::Wall;
-                    }
-                    _ => println!("No idea what to do with {}", c),
+    let mut spawns = Vec::new();
+    let mut took_goal = false;
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            let point = corner + Point::new(x as i32, y as i32);
+            let idx = mb.map.point2d_to_index(point);
+            // Legends are checked when the vaults are loaded.
+            let tile = &vault.legend[c];
+            mb.map.tiles[idx] = match tile {
+                VaultTile::Wall => TileType::Wall,
+                VaultTile::Door => TileType::Door,
+                VaultTile::ShallowWater => TileType::ShallowWater,
+                VaultTile::DeepWater => TileType::DeepWater,
+                VaultTile::Lava => TileType::Lava,
+                VaultTile::Rubble => TileType::Rubble,
+                VaultTile::Bridge => TileType::Bridge,
+                VaultTile::Grass => TileType::Grass,
+                _ => TileType::Floor,
+            };
+            match tile {
+                VaultTile::Monster => spawns.push((point, VaultSpawn::Monster)),
+                VaultTile::Item => spawns.push((point, VaultSpawn::Item)),
+                VaultTile::Template(name) => {
+                    spawns.push((point, VaultSpawn::Template(name.clone())))
+                }
+                VaultTile::Exit if goal => {
+                    mb.amulet_start = point;
+                    took_goal = true;
                 }
-                i += 1;
+                _ => {}
             }
         }
-    };
+    }
+
+    mb.vaults.push(PlacedVault {
+        name: vault.name.clone(),
+        area,
+        spawns,
+    });
+    took_goal
 }
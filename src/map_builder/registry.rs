@@ -0,0 +1,194 @@
+use std::ops::RangeInclusive;
+
+use super::{
+    CullUnreachable, MapArchitect, MapPipeline, Overlay, PlaceSpawns,
+    automata::CellularAutomataArchitect, bsp::BspArchitect, drunkard::DrunkArchitect,
+    empty::EmptyArchitect, maze::MazeArchitect, rooms::RoomsArchitect, voronoi::VoronoiArchitect,
+    wfc::WfcArchitect,
+};
+use crate::prelude::*;
+
+/// The most the weights of a set of choices can add up to, so that picking
+/// one fits in a single roll.
+pub const MAX_TOTAL_WEIGHT: u64 = i32::MAX as u64;
+
+/// Picks one of `choices` by weight, or `None` if the weights add up to 0.
+/// Rolled as an i32, like the rest of the game's rolls, so seeds keep their
+/// dungeons; the weights must add up to no more than `MAX_TOTAL_WEIGHT`.
+pub fn pick_weighted<T>(
+    rand: &mut RandomNumberGenerator,
+    choices: impl Iterator<Item = (T, u32)> + Clone,
+) -> Option<T> {
+    let total: u64 = choices.clone().map(|(_, weight)| u64::from(weight)).sum();
+    if total == 0 {
+        return None;
+    }
+    let total = i32::try_from(total).expect("weights add up to at most MAX_TOTAL_WEIGHT");
+    let mut roll = rand.range(0, total) as u32;
+    for (choice, weight) in choices {
+        if roll < weight {
+            return Some(choice);
+        }
+        roll -= weight;
+    }
+    unreachable!("the roll is below the total weight")
+}
+
+/// Makes a fresh architect for each level it builds.
+pub type ArchitectFactory = fn() -> Box<dyn MapArchitect>;
+
+/// An architect the game knows by name.
+#[derive(Clone, Debug)]
+pub struct ArchitectEntry {
+    pub name: &'static str,
+    /// How often it is picked, relative to the others, for levels whose
+    /// plan leaves the choice to the registry. 0 means only when asked for
+    /// by name.
+    pub weight: u32,
+    /// The depths, counting from 0, it is picked for.
+    pub depths: RangeInclusive<u32>,
+    pub build: ArchitectFactory,
+}
+
+/// Every architect a level can be built with. New generators are added with
+/// `register`, after which they can be named in dungeon plans and on the
+/// command line like the built-in ones.
+#[derive(Clone, Debug)]
+pub struct ArchitectRegistry {
+    entries: Vec<ArchitectEntry>,
+}
+
+impl Default for ArchitectRegistry {
+    /// The architects that ship with the game.
+    fn default() -> Self {
+        let mut registry = Self {
+            entries: Vec::new(),
+        };
+        registry.register("rooms", 1, 0..=u32::MAX, || {
+            Box::new(MapPipeline::new(Box::new(RoomsArchitect {})).with(PlaceDoors))
+        });
+        registry.register("automata", 1, 0..=u32::MAX, || {
+            Box::new(CellularAutomataArchitect {})
+        });
+        registry.register("drunkard", 1, 0..=u32::MAX, || Box::new(DrunkArchitect {}));
+        registry.register("bsp", 1, 0..=u32::MAX, || {
+            Box::new(
+                MapPipeline::new(Box::new(BspArchitect {}))
+                    .with(WidenCorridors)
+                    .with(PlaceDoors),
+            )
+        });
+        registry.register("wfc", 1, 0..=u32::MAX, || Box::new(WfcArchitect {}));
+        registry.register("maze", 1, 0..=u32::MAX, || {
+            Box::new(MazeArchitect {
+                braid: 50,
+                chambers: 4,
+            })
+        });
+        registry.register("voronoi", 1, 0..=u32::MAX, || Box::new(VoronoiArchitect {}));
+        // A perfect maze: one way through, and nowhere to catch your breath.
+        registry.register("labyrinth", 1, 1..=u32::MAX, || {
+            Box::new(MazeArchitect {
+                braid: 0,
+                chambers: 0,
+            })
+        });
+        // Caves with the halls of an older dungeon cut through them. The
+        // halls the caves never reach are filled back in.
+        registry.register("cave_halls", 1, 1..=u32::MAX, || {
+            Box::new(
+                MapPipeline::new(Box::new(DrunkArchitect {}))
+                    .with(Overlay::new(Box::new(BspArchitect {})))
+                    .with(CullUnreachable)
+                    .with(PlaceSpawns),
+            )
+        });
+        // Caves with lakes of deep water in them, and grass between.
+        registry.register("lakes", 1, 0..=u32::MAX, || {
+            Box::new(
+                MapPipeline::new(Box::new(CellularAutomataArchitect {}))
+                    .with(Pools::new(
+                        TileType::DeepWater,
+                        Some(TileType::ShallowWater),
+                        4,
+                    ))
+                    .with(Pools::new(TileType::Grass, None, 6)),
+            )
+        });
+        // Caverns with lava welling up through the floor, too dangerous for
+        // the first level.
+        registry.register("volcanic", 1, 1..=u32::MAX, || {
+            Box::new(
+                MapPipeline::new(Box::new(DrunkArchitect {})).with(Pools::new(
+                    TileType::Lava,
+                    Some(TileType::Rubble),
+                    3,
+                )),
+            )
+        });
+        // An open floor is only useful for trying things out.
+        registry.register("empty", 0, 0..=u32::MAX, || Box::new(EmptyArchitect {}));
+        registry
+    }
+}
+
+impl ArchitectRegistry {
+    /// Adds an architect, replacing any already registered under `name`.
+    /// Panics if that takes the weights of every architect past
+    /// `MAX_TOTAL_WEIGHT`.
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        weight: u32,
+        depths: RangeInclusive<u32>,
+        build: ArchitectFactory,
+    ) {
+        let entry = ArchitectEntry {
+            name,
+            weight,
+            depths,
+            build,
+        };
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        let total: u64 = self
+            .entries
+            .iter()
+            .map(|entry| u64::from(entry.weight))
+            .sum();
+        assert!(
+            total <= MAX_TOTAL_WEIGHT,
+            "architect weights add up to {total}, past {MAX_TOTAL_WEIGHT}"
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ArchitectEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Every registered name, in the order they were registered.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|entry| entry.name).collect()
+    }
+
+    /// The architects that can be picked at random for `depth`.
+    pub fn available(&self, depth: u32) -> impl Iterator<Item = &ArchitectEntry> + Clone {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.weight > 0 && entry.depths.contains(&depth))
+    }
+
+    /// Picks one of the architects available for `depth`, by weight.
+    pub fn pick(&self, rand: &mut RandomNumberGenerator, depth: u32) -> Option<&ArchitectEntry> {
+        pick_weighted(
+            rand,
+            self.available(depth).map(|entry| (entry, entry.weight)),
+        )
+    }
+}
@@ -4,14 +4,21 @@ use crate::prelude::*;
 pub struct RoomsArchitect {}
 
 impl MapArchitect for RoomsArchitect {
-    fn new(&mut self, rand: &mut RandomNumberGenerator) -> MapBuilder {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
         let mut mb = MapBuilder {
-            map: Map::new(),
+            map: Map::new(width, height),
             rooms: Vec::new(),
             amulet_start: Point::zero(),
             player_start: Point::zero(),
             monster_spawns: Vec::new(),
-        themes: super::themes::DungeonTheme::new(),
+            vaults: Vec::new(),
+            themes: super::themes::DungeonTheme::new(),
         };
 
         mb.fill(TileType::Wall);
@@ -21,7 +28,7 @@ impl MapArchitect for RoomsArchitect {
         mb.player_start = mb.rooms[0].center();
         mb.amulet_start = mb.find_most_distance();
 
-        for room in mb.rooms.iter().skip(1) {
+        for room in mb.rooms.iter().skip(1).take(monsters) {
             mb.monster_spawns.push(room.center());
         }
 
@@ -1,5 +1,26 @@
+#![allow(clippy::new_ret_no_self)]
 use crate::prelude::*;
 
+/// The glyph a tile is drawn with in themes that don't give it one of their
+/// own. Every theme has its own floors and walls; stairs, doors and terrain
+/// look the same wherever they are.
+pub fn default_glyph(tile_type: TileType) -> FontCharType {
+    match tile_type {
+        TileType::Floor => to_cp437('.'),
+        TileType::Wall => to_cp437('#'),
+        TileType::Exit => to_cp437('>'),
+        TileType::UpStairs => to_cp437('<'),
+        TileType::Door => to_cp437('+'),
+        TileType::OpenDoor => to_cp437('\''),
+        TileType::ShallowWater => to_cp437('░'),
+        TileType::DeepWater => to_cp437('≈'),
+        TileType::Lava => to_cp437('^'),
+        TileType::Rubble => to_cp437('*'),
+        TileType::Bridge => to_cp437('≡'),
+        TileType::Grass => to_cp437(':'),
+    }
+}
+
 pub struct DungeonTheme {}
 
 impl DungeonTheme {
@@ -9,12 +30,8 @@ impl DungeonTheme {
 }
 
 impl MapThemes for DungeonTheme {
-    fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
-        match tile_type {
-            TileType::Floor => to_cp437('.'),
-            TileType::Wall => to_cp437('#'),
-            TileType::Exit => to_cp437('>')
-        }
+    fn name(&self) -> &'static str {
+        "dungeon"
     }
 }
 
@@ -27,11 +44,15 @@ impl ForestTheme {
 }
 
 impl MapThemes for ForestTheme {
+    fn name(&self) -> &'static str {
+        "forest"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
             TileType::Floor => to_cp437(';'),
             TileType::Wall => to_cp437('"'),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
 }
@@ -45,11 +66,15 @@ impl CaveTheme {
 }
 
 impl MapThemes for CaveTheme {
+    fn name(&self) -> &'static str {
+        "cave"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
             TileType::Floor => to_cp437(','),
             TileType::Wall => to_cp437('%'),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
 }
@@ -63,11 +88,15 @@ impl DesertTheme {
 }
 
 impl MapThemes for DesertTheme {
+    fn name(&self) -> &'static str {
+        "desert"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
             TileType::Floor => to_cp437('~'),
             TileType::Wall => to_cp437('='),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
 }
@@ -81,11 +110,15 @@ impl IceTheme {
 }
 
 impl MapThemes for IceTheme {
+    fn name(&self) -> &'static str {
+        "ice"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
             TileType::Floor => to_cp437('·'),
             TileType::Wall => to_cp437('█'),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
 }
@@ -99,11 +132,15 @@ impl CryptTheme {
 }
 
 impl MapThemes for CryptTheme {
+    fn name(&self) -> &'static str {
+        "crypt"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
             TileType::Floor => to_cp437(','),
             TileType::Wall => to_cp437('X'),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
 }
@@ -117,11 +154,15 @@ impl SwampTheme {
 }
 
 impl MapThemes for SwampTheme {
+    fn name(&self) -> &'static str {
+        "swamp"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
             TileType::Floor => to_cp437('~'),
             TileType::Wall => to_cp437('"'),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
 }
@@ -135,11 +176,34 @@ impl LavaTheme {
 }
 
 impl MapThemes for LavaTheme {
+    fn name(&self) -> &'static str {
+        "lava"
+    }
+
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
         match tile_type {
-            TileType::Floor => to_cp437('.'),
             TileType::Wall => to_cp437('█'),
-            TileType::Exit => to_cp437('>')
+            _ => default_glyph(tile_type),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Names accepted by `theme_by_name`.
+pub const THEME_NAMES: [&str; 8] = [
+    "dungeon", "forest", "cave", "desert", "ice", "crypt", "swamp", "lava",
+];
+
+/// Looks a theme up by the name it reports from `MapThemes::name`.
+pub fn theme_by_name(name: &str) -> Option<Box<dyn MapThemes>> {
+    match name {
+        "dungeon" => Some(DungeonTheme::new()),
+        "forest" => Some(ForestTheme::new()),
+        "cave" => Some(CaveTheme::new()),
+        "desert" => Some(DesertTheme::new()),
+        "ice" => Some(IceTheme::new()),
+        "crypt" => Some(CryptTheme::new()),
+        "swamp" => Some(SwampTheme::new()),
+        "lava" => Some(LavaTheme::new()),
+        _ => None,
+    }
+}
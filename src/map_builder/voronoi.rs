@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// Tiles of map per cell; an 80 by 50 level gets 40 cells.
+const TILES_PER_CELL: i32 = 100;
+
+/// Scatters seeds over the map and gives every tile to the nearest one,
+/// making a hive of irregular cells. Walls run along the cell boundaries,
+/// and each pair of neighbouring cells gets one doorway between them.
+pub struct VoronoiArchitect {}
+
+impl MapArchitect for VoronoiArchitect {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
+        let mut mb = MapBuilder {
+            map: Map::new(width, height),
+            rooms: Vec::new(),
+            monster_spawns: Vec::new(),
+            vaults: Vec::new(),
+            player_start: Point::zero(),
+            amulet_start: Point::zero(),
+            themes: super::themes::DungeonTheme::new(),
+        };
+
+        let seeds: Vec<Point> = (0..(width * height / TILES_PER_CELL).max(2))
+            .map(|_| Point::new(rand.range(1, width - 1), rand.range(1, height - 1)))
+            .collect();
+        let owners = self.assign_cells(&mb.map, &seeds);
+        self.build_walls(&mut mb.map, &owners);
+        self.open_doorways(rand, &mut mb.map, &owners);
+
+        // Cells squeezed down to nothing but wall have no room.
+        let mut centers = Vec::new();
+        for (cell, seed) in seeds.iter().enumerate() {
+            let floor: Vec<Point> = (0..owners.len())
+                .filter(|idx| owners[*idx] == cell && mb.map.tiles[*idx] == TileType::Floor)
+                .map(|idx| mb.map.index_to_point2d(idx))
+                .collect();
+            let Some(first) = floor.first() else {
+                continue;
+            };
+            let (mut min, mut max) = (*first, *first);
+            for point in &floor {
+                min = Point::new(min.x.min(point.x), min.y.min(point.y));
+                max = Point::new(max.x.max(point.x), max.y.max(point.y));
+            }
+            mb.rooms
+                .push(Rect::with_exact(min.x, min.y, max.x + 1, max.y + 1));
+
+            let distance = |point: &Point| DistanceAlg::PythagorasSquared.distance2d(*seed, *point);
+            let center = floor
+                .iter()
+                .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+                .unwrap();
+            centers.push(*center);
+        }
+
+        mb.player_start = centers[0];
+        mb.amulet_start = mb.find_most_distance();
+        mb.monster_spawns = mb.spawn_monster(&mb.player_start, rand, monsters);
+
+        mb
+    }
+}
+
+impl VoronoiArchitect {
+    /// The index of the nearest seed, for every tile.
+    fn assign_cells(&mut self, map: &Map, seeds: &[Point]) -> Vec<usize> {
+        (0..map.tiles.len())
+            .map(|idx| {
+                let point = map.index_to_point2d(idx);
+                let distance =
+                    |seed: &Point| DistanceAlg::PythagorasSquared.distance2d(*seed, point);
+                (0..seeds.len())
+                    .min_by(|a, b| {
+                        distance(&seeds[*a])
+                            .partial_cmp(&distance(&seeds[*b]))
+                            .unwrap()
+                    })
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Floors every tile, then walls the map's edge and each tile whose
+    /// right or lower neighbour is in another cell. Checking only two sides
+    /// keeps the walls one tile thick while still separating every cell.
+    fn build_walls(&mut self, map: &mut Map, owners: &[usize]) {
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let idx = map.map_idx(x, y);
+                let edge = x == 0 || y == 0 || x == map.width - 1 || y == map.height - 1;
+                let boundary = [Point::new(x + 1, y), Point::new(x, y + 1)]
+                    .into_iter()
+                    .filter_map(|next| map.try_idx(next))
+                    .any(|next| owners[next] != owners[idx]);
+                map.tiles[idx] = if edge || boundary {
+                    TileType::Wall
+                } else {
+                    TileType::Floor
+                };
+            }
+        }
+    }
+
+    /// Opens one wall tile between each pair of cells that have floor on
+    /// either side of it.
+    fn open_doorways(&mut self, rand: &mut RandomNumberGenerator, map: &mut Map, owners: &[usize]) {
+        let mut candidates: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+        for y in 1..map.height - 1 {
+            for x in 1..map.width - 1 {
+                let idx = map.map_idx(x, y);
+                if map.tiles[idx] != TileType::Wall {
+                    continue;
+                }
+                for (a, b) in [
+                    (Point::new(x - 1, y), Point::new(x + 1, y)),
+                    (Point::new(x, y - 1), Point::new(x, y + 1)),
+                ] {
+                    let (a, b) = (map.point2d_to_index(a), map.point2d_to_index(b));
+                    let both_floor =
+                        map.tiles[a] == TileType::Floor && map.tiles[b] == TileType::Floor;
+                    if both_floor && owners[a] != owners[b] {
+                        let pair = (owners[a].min(owners[b]), owners[a].max(owners[b]));
+                        candidates.entry(pair).or_default().push(idx);
+                    }
+                }
+            }
+        }
+
+        for walls in candidates.values() {
+            let idx = walls[rand.random_slice_index(walls).unwrap()];
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
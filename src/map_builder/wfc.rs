@@ -0,0 +1,429 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    sync::OnceLock,
+};
+
+use super::{CullUnreachable, MapArchitect, MapModifier, bsp::BspArchitect};
+use crate::prelude::*;
+
+/// Hand-drawn maps the architect learns from, in the same format as the
+/// prefabs: `#` is a wall and `-` a floor.
+const SAMPLES: [(&str, i32, i32); 2] = [
+    (
+        "####################
+        #------######------#
+        #------######------#
+        #--------------#---#
+        #------######--#---#
+        ###-##########-#-###
+        ###-##########-#-###
+        ###------####------#
+        #####-#######-######
+        #-------###-------##
+        #-------###-------##
+        #-----------------##
+        #-------###-------##
+        ####################",
+        20,
+        14,
+    ),
+    (
+        "##################
+        #----------------#
+        #-##-##-##-##-##-#
+        #----------------#
+        #-##-##----##-##-#
+        #-##-##----##-##-#
+        #----------------#
+        #-##-##-##-##-##-#
+        #----------------#
+        ##################",
+        18,
+        10,
+    ),
+];
+
+/// The side of the square patterns learned from the samples.
+const PATTERN_SIZE: i32 = 3;
+/// Times the level is synthesised before falling back to another architect.
+const ATTEMPTS: usize = 3;
+/// Levels with more tiles than this, a little over the default size, take
+/// too long to synthesise, and are built by the fallback architect instead.
+const MAX_TILES: i32 = 6_000;
+/// Output whose open area is smaller than this share of the map is thrown away.
+const MIN_FLOOR_DIVISOR: usize = 5;
+
+/// Left, down, right and up; each direction's opposite is two along.
+const DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// Synthesises levels with Wave Function Collapse: every small square of
+/// the samples (in every rotation and reflection) becomes a pattern, and the
+/// level is filled with patterns that overlap the way they do in the
+/// samples. When that ends in a contradiction, or in too little connected
+/// floor, or the level is bigger than `MAX_TILES`, it is built by
+/// `BspArchitect` instead.
+pub struct WfcArchitect {}
+
+impl MapArchitect for WfcArchitect {
+    fn new(
+        &mut self,
+        rand: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        monsters: usize,
+    ) -> MapBuilder {
+        let mut mb = MapBuilder {
+            map: Map::new(width, height),
+            rooms: Vec::new(),
+            monster_spawns: Vec::new(),
+            vaults: Vec::new(),
+            player_start: Point::zero(),
+            amulet_start: Point::zero(),
+            themes: super::themes::DungeonTheme::new(),
+        };
+
+        if width * height > MAX_TILES {
+            return BspArchitect {}.new(rand, width, height, monsters);
+        }
+        // The samples never change, so they are only learned from once.
+        static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+        let patterns = PATTERNS.get_or_init(Patterns::learn);
+        for _ in 0..ATTEMPTS {
+            let Some(floor) = Wave::new(patterns, width, height, rand).collapse(rand) else {
+                continue;
+            };
+            mb.map.tiles = floor
+                .iter()
+                .map(|open| {
+                    if *open {
+                        TileType::Floor
+                    } else {
+                        TileType::Wall
+                    }
+                })
+                .collect();
+            self.wall_in(&mut mb.map);
+
+            let Some(start) = self.floor_nearest_middle(&mb.map) else {
+                continue;
+            };
+            mb.player_start = start;
+            CullUnreachable.apply(rand, &mut mb, monsters);
+            let floor = mb
+                .map
+                .tiles
+                .iter()
+                .filter(|tile| **tile == TileType::Floor)
+                .count();
+            if floor < mb.map.tiles.len() / MIN_FLOOR_DIVISOR {
+                continue;
+            }
+            mb.amulet_start = mb.find_most_distance();
+            mb.monster_spawns = mb.spawn_monster(&start, rand, monsters);
+            return mb;
+        }
+
+        BspArchitect {}.new(rand, width, height, monsters)
+    }
+}
+
+impl WfcArchitect {
+    fn wall_in(&mut self, map: &mut Map) {
+        for x in 0..map.width {
+            let (top, bottom) = (map.map_idx(x, 0), map.map_idx(x, map.height - 1));
+            map.tiles[top] = TileType::Wall;
+            map.tiles[bottom] = TileType::Wall;
+        }
+        for y in 0..map.height {
+            let (left, right) = (map.map_idx(0, y), map.map_idx(map.width - 1, y));
+            map.tiles[left] = TileType::Wall;
+            map.tiles[right] = TileType::Wall;
+        }
+    }
+
+    /// The player starts on the floor nearest the middle of the map.
+    fn floor_nearest_middle(&self, map: &Map) -> Option<Point> {
+        let center = Point::new(map.width / 2, map.height / 2);
+        map.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor)
+            .map(|(idx, _)| map.index_to_point2d(idx))
+            .min_by(|a, b| {
+                let distance = |point| DistanceAlg::Pythagoras.distance2d(center, point);
+                distance(*a).partial_cmp(&distance(*b)).unwrap()
+            })
+    }
+}
+
+/// The patterns found in the samples, how often each was seen, and which
+/// can sit next to which.
+struct Patterns {
+    /// Each pattern's tiles, row by row; `true` is floor.
+    tiles: Vec<Vec<bool>>,
+    weights: Vec<u32>,
+    /// For each direction and pattern, the patterns that can be its
+    /// neighbour in that direction.
+    neighbours: [Vec<Vec<usize>>; 4],
+}
+
+impl Patterns {
+    fn learn() -> Self {
+        let mut index = HashMap::new();
+        let mut tiles = Vec::new();
+        let mut weights = Vec::new();
+
+        for (sample, width, height) in SAMPLES {
+            let floor: Vec<bool> = sample
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| c == '-')
+                .collect();
+            debug_assert_eq!(floor.len(), (width * height) as usize);
+
+            for y in 0..=height - PATTERN_SIZE {
+                for x in 0..=width - PATTERN_SIZE {
+                    let window = pattern_from(|px, py| floor[((y + py) * width + x + px) as usize]);
+                    for variant in symmetries(window) {
+                        let id = *index.entry(variant.clone()).or_insert_with(|| {
+                            tiles.push(variant);
+                            weights.push(0);
+                            tiles.len() - 1
+                        });
+                        weights[id] += 1;
+                    }
+                }
+            }
+        }
+
+        let neighbours = DIRECTIONS.map(|(dx, dy)| {
+            (0..tiles.len())
+                .map(|first| {
+                    (0..tiles.len())
+                        .filter(|second| overlaps(&tiles[first], &tiles[*second], dx, dy))
+                        .collect()
+                })
+                .collect()
+        });
+
+        Self {
+            tiles,
+            weights,
+            neighbours,
+        }
+    }
+}
+
+fn pattern_from(tile: impl Fn(i32, i32) -> bool) -> Vec<bool> {
+    (0..PATTERN_SIZE * PATTERN_SIZE)
+        .map(|i| tile(i % PATTERN_SIZE, i / PATTERN_SIZE))
+        .collect()
+}
+
+/// `pattern` in all four rotations, and each of those mirrored.
+fn symmetries(pattern: Vec<bool>) -> Vec<Vec<bool>> {
+    let at = |pattern: &[bool], x: i32, y: i32| pattern[(y * PATTERN_SIZE + x) as usize];
+    let mut variants = vec![pattern];
+    for _ in 0..3 {
+        let last = variants.last().unwrap();
+        let rotated = pattern_from(|x, y| at(last, PATTERN_SIZE - 1 - y, x));
+        variants.push(rotated);
+    }
+    for i in 0..4 {
+        let mirrored = pattern_from(|x, y| at(&variants[i], PATTERN_SIZE - 1 - x, y));
+        variants.push(mirrored);
+    }
+    variants
+}
+
+/// Whether `second`, placed `dx`, `dy` from `first`, agrees with it
+/// wherever the two overlap.
+fn overlaps(first: &[bool], second: &[bool], dx: i32, dy: i32) -> bool {
+    for y in dy.max(0)..(PATTERN_SIZE + dy).min(PATTERN_SIZE) {
+        for x in dx.max(0)..(PATTERN_SIZE + dx).min(PATTERN_SIZE) {
+            if first[(y * PATTERN_SIZE + x) as usize]
+                != second[((y - dy) * PATTERN_SIZE + x - dx) as usize]
+            {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// The patterns each cell of the level could still hold. A cell is the
+/// top-left corner of its pattern, so there are fewer cells than tiles.
+struct Wave<'a> {
+    patterns: &'a Patterns,
+    width: i32,
+    height: i32,
+    map_width: i32,
+    map_height: i32,
+    possible: Vec<bool>,
+    remaining: Vec<usize>,
+    /// For each cell, pattern and direction, how many of the patterns still
+    /// possible one step back along that direction allow it.
+    support: Vec<[u16; 4]>,
+    banned: Vec<(usize, usize)>,
+    /// Undecided cells by how many patterns they had left when pushed, so
+    /// the most constrained is always on top. Cells are pushed again once
+    /// the patterns they lost have been propagated, and stale entries skipped.
+    undecided: BinaryHeap<Reverse<(usize, i32, usize)>>,
+    /// Cells that lost patterns since they were last pushed.
+    changed: Vec<usize>,
+    is_changed: Vec<bool>,
+    /// Breaks ties between cells with equally few patterns left, so they are
+    /// settled in a random order.
+    noise: Vec<i32>,
+}
+
+impl<'a> Wave<'a> {
+    fn new(
+        patterns: &'a Patterns,
+        map_width: i32,
+        map_height: i32,
+        rand: &mut RandomNumberGenerator,
+    ) -> Self {
+        let width = map_width - PATTERN_SIZE + 1;
+        let height = map_height - PATTERN_SIZE + 1;
+        let cells = (width * height) as usize;
+        let count = patterns.tiles.len();
+
+        // Looking back along a direction is looking in the opposite one.
+        let support: Vec<[u16; 4]> = (0..count)
+            .map(|pattern| {
+                [0, 1, 2, 3]
+                    .map(|direction| patterns.neighbours[(direction + 2) % 4][pattern].len() as u16)
+            })
+            .collect();
+        let noise: Vec<i32> = (0..cells).map(|_| rand.range(0, 1000)).collect();
+        let undecided = (0..cells)
+            .map(|cell| Reverse((count, noise[cell], cell)))
+            .collect();
+
+        Self {
+            patterns,
+            width,
+            height,
+            map_width,
+            map_height,
+            possible: vec![true; cells * count],
+            remaining: vec![count; cells],
+            support: support.repeat(cells),
+            banned: Vec::new(),
+            undecided,
+            noise,
+            changed: Vec::new(),
+            is_changed: vec![false; cells],
+        }
+    }
+
+    /// Settles every cell on one pattern and returns the level's tiles,
+    /// `true` for floor, or `None` if some cell runs out of patterns.
+    fn collapse(mut self, rand: &mut RandomNumberGenerator) -> Option<Vec<bool>> {
+        let count = self.patterns.tiles.len();
+
+        while let Some(Reverse((left, _, cell))) = self.undecided.pop() {
+            if left != self.remaining[cell] || left <= 1 {
+                continue;
+            }
+
+            let options: Vec<usize> = (0..count)
+                .filter(|pattern| self.possible[cell * count + pattern])
+                .collect();
+            let total: u32 = options.iter().map(|p| self.patterns.weights[*p]).sum();
+            let mut roll = rand.range(0, total as i32) as u32;
+            let mut chosen = options[0];
+            for pattern in &options {
+                let weight = self.patterns.weights[*pattern];
+                if roll < weight {
+                    chosen = *pattern;
+                    break;
+                }
+                roll -= weight;
+            }
+
+            for pattern in options.into_iter().filter(|p| *p != chosen) {
+                self.ban(cell, pattern);
+            }
+            if !self.propagate() {
+                return None;
+            }
+        }
+
+        Some(self.tiles())
+    }
+
+    fn ban(&mut self, cell: usize, pattern: usize) {
+        let count = self.patterns.tiles.len();
+        let idx = cell * count + pattern;
+        if !self.possible[idx] {
+            return;
+        }
+        self.possible[idx] = false;
+        self.support[idx] = [0; 4];
+        self.remaining[cell] -= 1;
+        self.banned.push((cell, pattern));
+        if !self.is_changed[cell] {
+            self.is_changed[cell] = true;
+            self.changed.push(cell);
+        }
+    }
+
+    /// Removes every pattern left without support by the bans so far.
+    /// Returns `false` on a contradiction.
+    fn propagate(&mut self) -> bool {
+        let count = self.patterns.tiles.len();
+        while let Some((cell, pattern)) = self.banned.pop() {
+            let (x, y) = (cell as i32 % self.width, cell as i32 / self.width);
+            for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= self.width || ny >= self.height {
+                    continue;
+                }
+                let neighbour = (ny * self.width + nx) as usize;
+                for other in &self.patterns.neighbours[direction][pattern] {
+                    let idx = neighbour * count + other;
+                    if self.support[idx][direction] == 0 {
+                        continue;
+                    }
+                    self.support[idx][direction] -= 1;
+                    if self.support[idx][direction] == 0 {
+                        self.ban(neighbour, *other);
+                    }
+                }
+            }
+            // Every cell that runs out of patterns is banned from on its
+            // way to none, so it is caught here.
+            if self.remaining[cell] == 0 {
+                return false;
+            }
+        }
+        for cell in self.changed.drain(..) {
+            self.is_changed[cell] = false;
+            let left = self.remaining[cell];
+            if left > 1 {
+                self.undecided.push(Reverse((left, self.noise[cell], cell)));
+            }
+        }
+        true
+    }
+
+    /// Reads the level off the settled cells. Tiles past the last cell in a
+    /// row or column come from the far side of that cell's pattern.
+    fn tiles(&self) -> Vec<bool> {
+        let count = self.patterns.tiles.len();
+        (0..self.map_width * self.map_height)
+            .map(|idx| {
+                let (x, y) = (idx % self.map_width, idx / self.map_width);
+                let (cx, cy) = (x.min(self.width - 1), y.min(self.height - 1));
+                let cell = (cy * self.width + cx) as usize;
+                let pattern = (0..count)
+                    .find(|pattern| self.possible[cell * count + pattern])
+                    .unwrap();
+                self.patterns.tiles[pattern][((y - cy) * PATTERN_SIZE + x - cx) as usize]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// How many messages the log keeps before the oldest are dropped.
+pub const MESSAGE_LOG_CAPACITY: usize = 500;
+
+/// What a message is about, which decides the color it is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MessageKind {
+    Info,
+    Attack,
+    Hurt,
+    Death,
+    Item,
+}
+
+impl MessageKind {
+    pub fn color(self) -> (u8, u8, u8) {
+        match self {
+            MessageKind::Info => WHITE,
+            MessageKind::Attack => ORANGE,
+            MessageKind::Hurt => RED,
+            MessageKind::Death => GRAY,
+            MessageKind::Item => GREEN,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub kind: MessageKind,
+    pub text: String,
+}
+
+/// Everything that has happened to the player this run, oldest first.
+/// Systems append to it; the HUD shows the tail and the history screen the rest.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MessageLog {
+    messages: Vec<Message>,
+}
+
+impl MessageLog {
+    pub fn add<S: Into<String>>(&mut self, kind: MessageKind, text: S) {
+        if self.messages.len() == MESSAGE_LOG_CAPACITY {
+            self.messages.remove(0);
+        }
+        self.messages.push(Message {
+            kind,
+            text: text.into(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// The last `count` messages, oldest first.
+    pub fn recent(&self, count: usize) -> &[Message] {
+        &self.messages[self.messages.len().saturating_sub(count)..]
+    }
+
+    /// Up to `count` messages ending `scroll` messages before the newest, oldest first.
+    pub fn page(&self, scroll: usize, count: usize) -> &[Message] {
+        let end = self.messages.len().saturating_sub(scroll);
+        &self.messages[end.saturating_sub(count)..end]
+    }
+}
@@ -0,0 +1,233 @@
+use std::{fmt, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const REPLAY_FILE: &str = "replay.ron";
+
+/// Bump this whenever the meaning of a recorded key changes.
+pub const REPLAY_VERSION: u32 = 4;
+
+/// A key the player pressed, and the player turn it was pressed on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub turn: u32,
+    pub key: String,
+}
+
+/// Every key `player_input_system` has consumed since the run started.
+/// Together with the seed and config this is enough to play the run back exactly,
+/// up to the point recording stopped, if it did.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub config: GameConfig,
+    pub inputs: Vec<ReplayInput>,
+    /// Set once something a replay can't reproduce, such as respawning
+    /// monsters or reloading a data file, changed the run.
+    #[serde(default)]
+    pub stopped: bool,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse(String),
+    Version { found: u32 },
+    UnknownKey { turn: u32, key: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "couldn't read replay: {err}"),
+            ReplayError::Parse(err) => write!(f, "replay is corrupt: {err}"),
+            ReplayError::Version { found } => write!(
+                f,
+                "replay was recorded by an incompatible version of the game \
+                 (replay format {found}, this build reads format {REPLAY_VERSION})"
+            ),
+            ReplayError::UnknownKey { turn, key } => {
+                write!(f, "replay presses an unknown key \"{key}\" on turn {turn}")
+            }
+        }
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: GameConfig) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            config,
+            inputs: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    pub fn record(&mut self, key: VirtualKeyCode) {
+        if self.stopped {
+            return;
+        }
+        self.inputs.push(ReplayInput {
+            turn: self.inputs.len() as u32,
+            key: format!("{key:?}"),
+        });
+    }
+
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        let replay: Replay =
+            ron::from_str(&text).map_err(|err| ReplayError::Parse(err.to_string()))?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::Version {
+                found: replay.version,
+            });
+        }
+        replay.keys()?;
+        Ok(replay)
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let text =
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default().depth_limit(2))
+                .map_err(std::io::Error::other)?;
+        fs::write(path, text)
+    }
+
+    /// The recorded keys, in the order they were pressed.
+    pub fn keys(&self) -> Result<Vec<VirtualKeyCode>, ReplayError> {
+        self.inputs
+            .iter()
+            .map(|input| {
+                key_from_name(&input.key).ok_or_else(|| ReplayError::UnknownKey {
+                    turn: input.turn,
+                    key: input.key.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Writes the current run's replay to `REPLAY_FILE`, for attaching to bug reports.
+pub fn write_replay(resources: &Resources) {
+    if let Some(replay) = resources.get::<Replay>()
+        && let Err(err) = replay.write(REPLAY_FILE)
+    {
+        eprintln!("Couldn't write {REPLAY_FILE}: {err}");
+    }
+}
+
+/// Stops recording the current run's replay, because `why` changed the run
+/// in a way the replay can't reproduce, and says so in the message log. The
+/// keys recorded so far still play the run back up to here.
+pub fn stop_recording(resources: &Resources, why: &str) {
+    let Some(mut replay) = resources.get_mut::<Replay>() else {
+        return;
+    };
+    if replay.stopped {
+        return;
+    }
+    replay.stopped = true;
+    resources.get_mut::<MessageLog>().unwrap().add(
+        MessageKind::Info,
+        format!("{why}, so the replay stops recording here."),
+    );
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        /// Parses a key back from the `Debug` name it was recorded under.
+        fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+key_names![
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+    Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp,
+    Left, Up, Right, Down, Back, Return, Space, Compose, Caret,
+    Numlock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8,
+    Numpad9, NumpadAdd, NumpadDivide, NumpadDecimal, NumpadComma, NumpadEnter, NumpadEquals,
+    NumpadMultiply, NumpadSubtract,
+    AbntC1, AbntC2, Apostrophe, Apps, Asterisk, At, Ax, Backslash, Calculator, Capital, Colon,
+    Comma, Convert, Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl, LShift, LWin, Mail,
+    MediaSelect, MediaStop, Minus, Mute, MyComputer, NavigateForward, NavigateBackward,
+    NextTrack, NoConvert, OEM102, Period, PlayPause, Plus, Power, PrevTrack, RAlt, RBracket,
+    RControl, RShift, RWin, Semicolon, Slash, Sleep, Stop, Sysrq, Tab, Underline, Unlabeled,
+    VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward, WebHome, WebRefresh,
+    WebSearch, WebStop, Yen, Copy, Paste, Cut,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn replay_round_trips_through_a_file() {
+        let keys = [
+            VirtualKeyCode::Left,
+            VirtualKeyCode::G,
+            VirtualKeyCode::Key1,
+            VirtualKeyCode::Period,
+        ];
+        let mut replay = Replay::new(7, GameConfig::default());
+        for key in keys {
+            replay.record(key);
+        }
+
+        let path = temp_path("round-trip.ron");
+        replay.write(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.keys().unwrap(), keys);
+    }
+
+    #[test]
+    fn load_refuses_other_versions() {
+        let mut replay = Replay::new(7, GameConfig::default());
+        replay.version = REPLAY_VERSION + 1;
+
+        let path = temp_path("version.ron");
+        replay.write(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            loaded,
+            Err(ReplayError::Version { found }) if found == REPLAY_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn keys_reject_unknown_names() {
+        let mut replay = Replay::new(7, GameConfig::default());
+        replay.inputs.push(ReplayInput {
+            turn: 0,
+            key: "Hyper".to_string(),
+        });
+
+        assert!(matches!(
+            replay.keys(),
+            Err(ReplayError::UnknownKey { turn: 0, .. })
+        ));
+    }
+}
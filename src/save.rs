@@ -0,0 +1,329 @@
+use std::{fmt, fs, path::Path};
+
+use legion::serialize::Canon;
+use ron::value::RawValue;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub const SAVE_FILE: &str = "savegame.ron";
+
+/// Bump this whenever a saved component or resource changes shape, so that
+/// saves written by an older build are rejected instead of misread.
+pub const SAVE_VERSION: u32 = 12;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    Parse(String),
+    Version { found: u32 },
+    UnknownTheme(String),
+    Data(DataError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "couldn't read {SAVE_FILE}: {err}"),
+            LoadError::Parse(err) => write!(f, "{SAVE_FILE} is corrupt: {err}"),
+            LoadError::Version { found } => write!(
+                f,
+                "{SAVE_FILE} was written by an incompatible version of the game \
+                 (save format {found}, this build reads format {SAVE_VERSION})"
+            ),
+            LoadError::UnknownTheme(name) => {
+                write!(f, "{SAVE_FILE} uses an unknown map theme \"{name}\"")
+            }
+            LoadError::Data(err) => {
+                write!(
+                    f,
+                    "{SAVE_FILE} was played with data files that no longer load: {err}"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct SaveOut<'a, W: Serialize> {
+    version: u32,
+    seed: u64,
+    map: &'a Map,
+    theme: &'a str,
+    camera: &'a Camera,
+    turn_state: TurnState,
+    rng: &'a RandomNumberGenerator,
+    replay: &'a Replay,
+    config: &'a GameConfig,
+    log: &'a MessageLog,
+    spawn_points: &'a SpawnPoints,
+    world: W,
+    levels: Vec<LevelOut<'a, W>>,
+}
+
+/// A level in the `LevelStore`, the player's own level being saved separately.
+#[derive(Serialize)]
+struct LevelOut<'a, W: Serialize> {
+    depth: u32,
+    map: &'a Map,
+    theme: &'a str,
+    spawn_points: &'a SpawnPoints,
+    stairs: Point,
+    world: W,
+}
+
+#[derive(Deserialize)]
+struct SaveIn {
+    seed: u64,
+    map: Map,
+    theme: String,
+    camera: Camera,
+    turn_state: TurnState,
+    rng: RandomNumberGenerator,
+    replay: Replay,
+    config: GameConfig,
+    log: MessageLog,
+    spawn_points: SpawnPoints,
+    world: Box<RawValue>,
+    levels: Vec<LevelIn>,
+}
+
+#[derive(Deserialize)]
+struct LevelIn {
+    depth: u32,
+    map: Map,
+    theme: String,
+    spawn_points: SpawnPoints,
+    stairs: Point,
+    world: Box<RawValue>,
+}
+
+/// Every component in `components.rs`, plus `Point`, keyed by a stable name.
+fn registry() -> Registry<String> {
+    let mut registry = Registry::<String>::default();
+    registry.register::<Point>("point".to_string());
+    registry.register::<Render>("render".to_string());
+    registry.register::<Player>("player".to_string());
+    registry.register::<Enemy>("enemy".to_string());
+    registry.register::<MovingRandomly>("moving_randomly".to_string());
+    registry.register::<WantsToMove>("wants_to_move".to_string());
+    registry.register::<Health>("health".to_string());
+    registry.register::<Name>("name".to_string());
+    registry.register::<WantsToAttact>("wants_to_attack".to_string());
+    registry.register::<ChasingPlayer>("chasing_player".to_string());
+    registry.register::<Stationary>("stationary".to_string());
+    registry.register::<RangedAttacker>("ranged_attacker".to_string());
+    registry.register::<Cowardly>("cowardly".to_string());
+    registry.register::<OpensDoors>("opens_doors".to_string());
+    registry.register::<FromVault>("from_vault".to_string());
+    registry.register::<Movement>("movement".to_string());
+    registry.register::<Item>("item".to_string());
+    registry.register::<AmuletOfYala>("amulet_of_yala".to_string());
+    registry.register::<FieldOfView>("field_of_view".to_string());
+    registry.register::<ProvideHealing>("provide_healing".to_string());
+    registry.register::<ProvidedDungeonMap>("provided_dungeon_map".to_string());
+    registry.register::<Carried>("carried".to_string());
+    registry.register::<ActivateItem>("activate_item".to_string());
+    registry.register::<Damage>("damage".to_string());
+    registry.register::<Weapon>("weapon".to_string());
+    registry
+}
+
+pub fn has_save() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+pub fn delete_save() {
+    let _ = fs::remove_file(SAVE_FILE);
+}
+
+/// Writes the game in progress to `SAVE_FILE`.
+pub fn save_game(ecs: &World, resources: &Resources) -> std::io::Result<()> {
+    fs::write(SAVE_FILE, to_ron(ecs, resources)?)
+}
+
+fn to_ron(ecs: &World, resources: &Resources) -> std::io::Result<String> {
+    let registry = registry();
+    let canon = Canon::default();
+    let theme = resources.get::<Box<dyn MapThemes>>().unwrap();
+    let store = resources.get::<LevelStore>().unwrap();
+
+    let save = SaveOut {
+        version: SAVE_VERSION,
+        seed: resources.get::<Seed>().unwrap().0,
+        map: &resources.get::<Map>().unwrap(),
+        theme: theme.name(),
+        camera: &resources.get::<Camera>().unwrap(),
+        turn_state: *resources.get::<TurnState>().unwrap(),
+        rng: &resources.get::<RandomNumberGenerator>().unwrap(),
+        replay: &resources.get::<Replay>().unwrap(),
+        config: &resources.get::<GameConfig>().unwrap(),
+        log: &resources.get::<MessageLog>().unwrap(),
+        spawn_points: &resources.get::<SpawnPoints>().unwrap(),
+        world: ecs.as_serializable(any(), &registry, &canon),
+        levels: store
+            .levels
+            .iter()
+            .map(|(depth, level)| LevelOut {
+                depth: *depth,
+                map: &level.map,
+                theme: level.theme.name(),
+                spawn_points: &level.spawn_points,
+                stairs: level.stairs,
+                world: level.world.as_serializable(any(), &registry, &canon),
+            })
+            .collect(),
+    };
+
+    ron::to_string(&save).map_err(std::io::Error::other)
+}
+
+/// Reads `SAVE_FILE` back into a fresh world and resources, with levels
+/// built from now on by `architects`.
+pub fn load_game(architects: ArchitectRegistry) -> Result<(World, Resources), LoadError> {
+    let text = fs::read_to_string(SAVE_FILE).map_err(LoadError::Io)?;
+    from_ron(&text, architects)
+}
+
+fn from_ron(text: &str, architects: ArchitectRegistry) -> Result<(World, Resources), LoadError> {
+    let header: SaveHeader =
+        ron::from_str(text).map_err(|err| LoadError::Parse(err.to_string()))?;
+    if header.version != SAVE_VERSION {
+        return Err(LoadError::Version {
+            found: header.version,
+        });
+    }
+
+    let save: SaveIn = ron::from_str(text).map_err(|err| LoadError::Parse(err.to_string()))?;
+    let theme = theme_by_name(&save.theme).ok_or(LoadError::UnknownTheme(save.theme))?;
+    let data = GameData::load(&save.config, architects).map_err(LoadError::Data)?;
+
+    let registry = registry();
+    let canon = Canon::default();
+    let read_world = |world: &RawValue| -> Result<World, LoadError> {
+        ron::Options::default()
+            .from_str_seed(world.get_ron(), registry.as_deserialize(&canon))
+            .map_err(|err| LoadError::Parse(err.to_string()))
+    };
+    let ecs = read_world(&save.world)?;
+
+    let mut store = LevelStore::default();
+    for level in save.levels {
+        let theme = theme_by_name(&level.theme).ok_or(LoadError::UnknownTheme(level.theme))?;
+        let world = read_world(&level.world)?;
+        store.levels.insert(
+            level.depth,
+            StoredLevel {
+                map: level.map,
+                theme,
+                spawn_points: level.spawn_points,
+                stairs: level.stairs,
+                world,
+            },
+        );
+    }
+
+    let mut resources = Resources::default();
+    resources.insert(save.map);
+    resources.insert(theme);
+    resources.insert(store);
+    resources.insert(save.camera);
+    resources.insert(save.turn_state);
+    resources.insert(save.rng);
+    resources.insert(Seed(save.seed));
+    resources.insert(save.replay);
+    resources.insert(save.config);
+    resources.insert(data.templates);
+    resources.insert(data.plan);
+    resources.insert(data.vaults);
+    resources.insert(data.architects);
+    resources.insert(save.log);
+    resources.insert(save.spawn_points);
+    resources.insert(Events::default());
+
+    Ok((ecs, resources))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A run on its second level, so there is a stored level to save too.
+    fn game() -> (World, Resources) {
+        let config = GameConfig::default();
+        let data = GameData::load(&config, ArchitectRegistry::default()).unwrap();
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        new_game(&mut ecs, &mut resources, 42, config, data);
+        advance_level(&mut ecs, &mut resources);
+        (ecs, resources)
+    }
+
+    fn player(ecs: &World) -> (Point, Player, Health) {
+        <(&Point, &Player, &Health)>::query()
+            .iter(ecs)
+            .map(|(pos, player, health)| (*pos, *player, *health))
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn a_saved_game_loads_back_as_it_was() {
+        let (ecs, resources) = game();
+        let text = to_ron(&ecs, &resources).unwrap();
+        let (loaded_ecs, loaded) = from_ron(&text, ArchitectRegistry::default()).unwrap();
+
+        assert_eq!(player(&loaded_ecs), player(&ecs));
+        assert_eq!(loaded_ecs.len(), ecs.len());
+        assert_eq!(loaded.get::<Seed>().unwrap().0, 42);
+        assert_eq!(
+            *loaded.get::<TurnState>().unwrap(),
+            *resources.get::<TurnState>().unwrap()
+        );
+        assert_eq!(
+            loaded.get::<Map>().unwrap().tiles,
+            resources.get::<Map>().unwrap().tiles
+        );
+        assert_eq!(
+            *loaded.get::<SpawnPoints>().unwrap(),
+            *resources.get::<SpawnPoints>().unwrap()
+        );
+        assert_eq!(
+            loaded.get::<Box<dyn MapThemes>>().unwrap().name(),
+            resources.get::<Box<dyn MapThemes>>().unwrap().name()
+        );
+
+        let stored = &loaded.get::<LevelStore>().unwrap().levels[&0];
+        let original = &resources.get::<LevelStore>().unwrap().levels[&0];
+        assert_eq!(stored.map.tiles, original.map.tiles);
+        assert_eq!(stored.world.len(), original.world.len());
+
+        // The rolls carry on where they left off.
+        let next_roll = |resources: &Resources| {
+            resources
+                .get_mut::<RandomNumberGenerator>()
+                .unwrap()
+                .next_u64()
+        };
+        assert_eq!(next_roll(&loaded), next_roll(&resources));
+    }
+
+    #[test]
+    fn refuses_saves_from_other_versions() {
+        let (ecs, resources) = game();
+        let text = to_ron(&ecs, &resources).unwrap().replacen(
+            &format!("version:{SAVE_VERSION}"),
+            &format!("version:{}", SAVE_VERSION + 1),
+            1,
+        );
+        assert!(matches!(
+            from_ron(&text, ArchitectRegistry::default()),
+            Err(LoadError::Version { found }) if found == SAVE_VERSION + 1
+        ));
+    }
+}
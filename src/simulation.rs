@@ -0,0 +1,173 @@
+use crate::prelude::*;
+
+/// How a headless run came to an end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Victory,
+    GameOver,
+    OutOfInput,
+}
+
+/// Drives the game without a `BTerm` window: no consoles, no draw batches,
+/// just the ECS and the turn structure, fed from a scripted stream of keys.
+pub struct Simulation {
+    ecs: World,
+    resources: Resources,
+    input_system: Schedule,
+    player_system: Schedule,
+    monster_system: Schedule,
+    turns: u32,
+}
+
+impl Simulation {
+    pub fn new(seed: u64, config: GameConfig, data: GameData) -> Self {
+        let mut ecs = World::default();
+        let mut resources = Resources::default();
+        new_game(&mut ecs, &mut resources, seed, config, data);
+
+        Self {
+            ecs,
+            resources,
+            input_system: build_input_scheduler(false),
+            player_system: build_player_scheduler(false),
+            monster_system: build_monster_scheduler(false),
+            turns: 0,
+        }
+    }
+
+    pub fn turn_state(&self) -> TurnState {
+        *self.resources.get::<TurnState>().unwrap()
+    }
+
+    /// Number of keys the player has acted on so far.
+    pub fn turns(&self) -> u32 {
+        self.turns
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.resources.get::<Seed>().unwrap().0
+    }
+
+    pub fn map_level(&self) -> u32 {
+        <&Player>::query()
+            .iter(&self.ecs)
+            .map(|player| player.map_level)
+            .next()
+            .unwrap_or(0)
+    }
+
+    /// Feeds a single key to the player and advances the game until it is
+    /// waiting for input again, or has ended.
+    pub fn step(&mut self, key: VirtualKeyCode) -> TurnState {
+        if self.turn_state() != TurnState::AwaitingInput {
+            return self.turn_state();
+        }
+
+        self.resources.insert(Some(key));
+        self.input_system
+            .execute(&mut self.ecs, &mut self.resources);
+        self.resources.insert(None::<VirtualKeyCode>);
+        self.turns += 1;
+
+        loop {
+            match self.turn_state() {
+                TurnState::PlayerTurn => self
+                    .player_system
+                    .execute(&mut self.ecs, &mut self.resources),
+                TurnState::MonsterTurn => self
+                    .monster_system
+                    .execute(&mut self.ecs, &mut self.resources),
+                TurnState::NextLevel => advance_level(&mut self.ecs, &mut self.resources),
+                TurnState::PreviousLevel => {
+                    return_to_level_above(&mut self.ecs, &mut self.resources)
+                }
+                state => return state,
+            }
+        }
+    }
+
+    /// Plays keys until the game is won or lost, or the script runs dry.
+    pub fn run<I>(&mut self, inputs: I) -> Outcome
+    where
+        I: IntoIterator<Item = VirtualKeyCode>,
+    {
+        for key in inputs {
+            match self.step(key) {
+                TurnState::Victory => return Outcome::Victory,
+                TurnState::GameOver => return Outcome::GameOver,
+                _ => {}
+            }
+        }
+        Outcome::OutOfInput
+    }
+}
+
+/// An endless script of keys a wandering player might press: mostly moves,
+/// with the occasional pickup or item use.
+pub fn random_inputs(mut rng: RandomNumberGenerator) -> impl Iterator<Item = VirtualKeyCode> {
+    std::iter::repeat_with(move || match rng.range(0, 12) {
+        0..=2 => VirtualKeyCode::Left,
+        3..=5 => VirtualKeyCode::Right,
+        6 | 7 => VirtualKeyCode::Up,
+        8 | 9 => VirtualKeyCode::Down,
+        10 => VirtualKeyCode::G,
+        _ => VirtualKeyCode::Key0,
+    })
+}
+
+/// Plays a recorded run back without a window and prints how it ended.
+pub fn run_headless_replay(replay: &Replay, data: &GameData) -> Result<(), ReplayError> {
+    let keys = replay.keys()?;
+    let mut sim = Simulation::new(replay.seed, replay.config.clone(), data.clone());
+    let outcome = sim.run(keys);
+    println!(
+        "replay (seed {}): {:?} on level {} after {} turns",
+        sim.seed(),
+        outcome,
+        sim.map_level() + 1,
+        sim.turns()
+    );
+    Ok(())
+}
+
+/// Plays `games` headless games of at most `max_turns` keys each and prints a
+/// one-line summary per game, followed by the totals. Game `n` is played with
+/// `first_seed + n`, for both the dungeon and its input script, so any single
+/// game can be replayed on its own with that seed.
+pub fn run_headless(
+    first_seed: u64,
+    games: u32,
+    max_turns: u32,
+    config: &GameConfig,
+    data: &GameData,
+) {
+    let (mut won, mut lost) = (0, 0);
+
+    for game in 0..games {
+        let seed = first_seed.wrapping_add(u64::from(game));
+        let mut sim = Simulation::new(seed, config.clone(), data.clone());
+        let inputs = random_inputs(RandomNumberGenerator::seeded(seed));
+        let outcome = sim.run(inputs.take(max_turns as usize));
+        match outcome {
+            Outcome::Victory => won += 1,
+            Outcome::GameOver => lost += 1,
+            Outcome::OutOfInput => {}
+        }
+        println!(
+            "game {} (seed {}): {:?} on level {} after {} turns",
+            game + 1,
+            sim.seed(),
+            outcome,
+            sim.map_level() + 1,
+            sim.turns()
+        );
+    }
+
+    println!(
+        "{} games: {} won, {} lost, {} unfinished",
+        games,
+        won,
+        lost,
+        games - won - lost
+    );
+}
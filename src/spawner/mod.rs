@@ -1,11 +1,12 @@
 use legion::world::SubWorld;
 mod template;
 
-use crate::{prelude::*, spawner::template::Templates};
+use crate::prelude::*;
+pub use template::{AiKind, EFFECT_NAMES, Templates};
 
-pub fn spawn_player(ecs: &mut World, pos: Point) {
+pub fn spawn_player(ecs: &mut World, pos: Point, map_level: u32) {
     ecs.push((
-        Player { map_level: 0 },
+        Player { map_level },
         pos,
         Render {
             color: ColorPair::new(WHITE, BLACK),
@@ -25,10 +26,22 @@ pub fn spawn_level(
     rand: &mut RandomNumberGenerator,
     level: usize,
     spawn_points: &[Point],
+    templates: &Templates,
     resources: &mut Resources,
 ) {
-    let template = Templates::load();
-    template.spawn_entities(ecs, rand, level, spawn_points, resources);
+    templates.spawn_entities(ecs, rand, level, spawn_points, resources);
+}
+
+/// Spawns what the level's prefab vaults hold.
+pub fn spawn_vaults(
+    ecs: &mut World,
+    rand: &mut RandomNumberGenerator,
+    level: usize,
+    vaults: &[PlacedVault],
+    templates: &Templates,
+    resources: &mut Resources,
+) {
+    templates.spawn_vault_contents(ecs, rand, level, vaults, resources);
 }
 
 pub fn spawn_amulet_of_yala(ecs: &mut World, pos: Point) {
@@ -1,8 +1,7 @@
 #![allow(unused)]
-use std::{collections::HashSet, fs::File};
+use std::collections::{HashMap, HashSet};
 
 use legion::systems::CommandBuffer;
-use ron::de::from_reader;
 use serde::Deserialize;
 
 use crate::prelude::*;
@@ -17,6 +16,18 @@ pub struct Template {
     pub provides: Option<Vec<(String, i32)>>,
     pub hp: Option<i32>,
     pub base_damage: Option<i32>,
+    /// Foreground and background colors as `"#rrggbb"`; white on black if left out.
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    /// How far an enemy can see; `DEFAULT_VISION` if left out.
+    pub vision: Option<i32>,
+    /// How an enemy behaves; it chases the player if left out.
+    pub ai: Option<AiKind>,
+    /// Whether an enemy opens closed doors it walks into.
+    #[serde(default)]
+    pub opens_doors: bool,
+    /// How an enemy gets about; it walks if left out.
+    pub movement: Option<Movement>,
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq)]
@@ -25,15 +36,130 @@ pub enum EntityType {
     Item,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum AiKind {
+    /// Hunts the player down once it sees them.
+    Chase,
+    /// Wanders about, ignoring the player.
+    Random,
+    /// Never moves, but hits the player if they come close.
+    Stationary,
+    /// Attacks the player from up to this many tiles away, and closes in when further.
+    Ranged(i32),
+    /// Runs from the player, and only fights back when cornered.
+    Cowardly,
+}
+
+pub const DEFAULT_VISION: i32 = 6;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Templates {
     pub entities: Vec<Template>,
 }
 
+/// The effects a template can list under `provides`.
+pub const EFFECT_NAMES: [&str; 2] = ["Healing", "MagicMap"];
+
 impl Templates {
-    pub fn load() -> Self {
-        let file = File::open("resources/template.ron").expect("Couldn't find specified file");
-        from_reader(file).expect("Unable to load template")
+    /// Reads and validates the templates at `path`.
+    pub fn load(path: &str) -> Result<Self, DataFileError> {
+        let templates: Templates = read_data_file(path)?;
+        let problems = templates.validate();
+        valid(templates, problems)
+    }
+
+    /// Every problem that would otherwise surface mid-game, in file order.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Problems::new("entry");
+        let mut first_entry_named = HashMap::new();
+
+        for (idx, template) in self.entities.iter().enumerate() {
+            let entry = idx + 1;
+            let mut problem = problems.entry(entry, Some(&template.name));
+
+            if template.name.trim().is_empty() {
+                problem.add("name", "must not be empty".to_string());
+            } else if let Some(first) = first_entry_named.get(&template.name) {
+                problem.add("name", format!("duplicates the name of entry {first}"));
+            } else {
+                first_entry_named.insert(template.name.clone(), entry);
+            }
+
+            if template.levels.is_empty() {
+                problem.add("levels", "must list at least one level".to_string());
+            }
+
+            if template.frequency < 1 {
+                problem.add(
+                    "frequency",
+                    format!("is {}, but must be at least 1", template.frequency),
+                );
+            }
+
+            match (&template.entity_type, template.hp) {
+                (EntityType::Enemy, None) => {
+                    problem.add("hp", "enemies need hit points".to_string());
+                }
+                (EntityType::Enemy, Some(hp)) if hp < 1 => {
+                    problem.add("hp", format!("is {hp}, but must be at least 1"));
+                }
+                (EntityType::Item, Some(_)) => {
+                    problem.add("hp", "items don't have hit points".to_string());
+                }
+                _ => {}
+            }
+
+            for (field, color) in [("fg", &template.fg), ("bg", &template.bg)] {
+                if let Some(color) = color
+                    && RGB::from_hex(color).is_err()
+                {
+                    problem.add(field, format!("\"{color}\" is not a \"#rrggbb\" color"));
+                }
+            }
+
+            if template.entity_type == EntityType::Item {
+                if template.vision.is_some() {
+                    problem.add("vision", "only enemies can see".to_string());
+                }
+                if template.ai.is_some() {
+                    problem.add("ai", "only enemies have an AI".to_string());
+                }
+                if template.opens_doors {
+                    problem.add("opens_doors", "only enemies open doors".to_string());
+                }
+                if template.movement.is_some() {
+                    problem.add("movement", "only enemies move".to_string());
+                }
+            }
+
+            let vision = template.vision.unwrap_or(DEFAULT_VISION);
+            if vision < 1 {
+                problem.add("vision", format!("is {vision}, but must be at least 1"));
+            }
+
+            if let Some(AiKind::Ranged(range)) = template.ai
+                && !(1..=vision).contains(&range)
+            {
+                problem.add(
+                    "ai",
+                    format!("Ranged({range}) must reach between 1 and its vision of {vision}"),
+                );
+            }
+
+            for (effect, _) in template.provides.iter().flatten() {
+                if !EFFECT_NAMES.contains(&effect.as_str()) {
+                    problem.add(
+                        "provides",
+                        format!(
+                            "unknown effect \"{effect}\" (expected one of {})",
+                            EFFECT_NAMES.join(", ")
+                        ),
+                    );
+                }
+            }
+        }
+
+        problems.into_vec()
     }
 
     pub fn spawn_entities(
@@ -44,6 +170,34 @@ impl Templates {
         spawn_points: &[Point],
         resources: &mut Resources,
     ) {
+        self.spawn_matching(ecs, rng, level, spawn_points, resources, |_| true);
+    }
+
+    /// Rolls every spawn point as `spawn_entities` does, but only spawns the
+    /// enemies, so the items already lying around the level are left alone.
+    /// Returns how many enemies were spawned.
+    pub fn spawn_enemies(
+        &self,
+        ecs: &mut World,
+        rng: &mut RandomNumberGenerator,
+        level: usize,
+        spawn_points: &[Point],
+        resources: &mut Resources,
+    ) -> usize {
+        self.spawn_matching(ecs, rng, level, spawn_points, resources, |template| {
+            template.entity_type == EntityType::Enemy
+        })
+    }
+
+    fn spawn_matching(
+        &self,
+        ecs: &mut World,
+        rng: &mut RandomNumberGenerator,
+        level: usize,
+        spawn_points: &[Point],
+        resources: &mut Resources,
+        keep: impl Fn(&Template) -> bool,
+    ) -> usize {
         let mut available_entities = Vec::new();
 
         self.entities
@@ -55,14 +209,61 @@ impl Templates {
                 }
             });
 
+        let mut spawned = 0;
         let mut commands = CommandBuffer::new(ecs);
         spawn_points.iter().for_each(|pt| {
-            if let Some(entity) = rng.random_slice_entry(&available_entities) {
+            if let Some(entity) = rng.random_slice_entry(&available_entities)
+                && keep(entity)
+            {
                 self.spawn_entity(pt, entity, &mut commands);
+                spawned += 1;
             };
         });
 
         commands.flush(ecs, resources);
+        spawned
+    }
+
+    /// Spawns each vault's monsters and items, rolled from the templates for
+    /// `level`, and the templates it names. A template that has since been
+    /// edited out of the file is skipped.
+    pub fn spawn_vault_contents(
+        &self,
+        ecs: &mut World,
+        rng: &mut RandomNumberGenerator,
+        level: usize,
+        vaults: &[PlacedVault],
+        resources: &mut Resources,
+    ) {
+        let mut commands = CommandBuffer::new(ecs);
+        for (pt, spawn) in vaults.iter().flat_map(|vault| &vault.spawns) {
+            let template = match spawn {
+                VaultSpawn::Monster => self.roll(rng, level, EntityType::Enemy),
+                VaultSpawn::Item => self.roll(rng, level, EntityType::Item),
+                VaultSpawn::Template(name) => self.entities.iter().find(|t| &t.name == name),
+            };
+            if let Some(template) = template {
+                let entity = self.spawn_entity(pt, template, &mut commands);
+                commands.add_component(entity, FromVault);
+            }
+        }
+        commands.flush(ecs, resources);
+    }
+
+    /// One of the templates of `kind` for `level`, picked by frequency.
+    fn roll(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        level: usize,
+        kind: EntityType,
+    ) -> Option<&Template> {
+        let available: Vec<&Template> = self
+            .entities
+            .iter()
+            .filter(|t| t.entity_type == kind && t.levels.contains(&level))
+            .flat_map(|t| std::iter::repeat_n(t, t.frequency as usize))
+            .collect();
+        rng.random_slice_entry(&available).copied()
     }
 
     pub fn spawn_entity(
@@ -70,11 +271,17 @@ impl Templates {
         pt: &Point,
         template: &Template,
         commands: &mut legion::systems::CommandBuffer,
-    ) {
+    ) -> Entity {
+        // Colors are checked when templates are loaded.
+        let color = |hex: &Option<String>, default| {
+            hex.as_deref()
+                .and_then(|hex| RGB::from_hex(hex).ok())
+                .unwrap_or_else(|| RGB::named(default))
+        };
         let entity = commands.push((
-            pt.clone(),
+            *pt,
             Render {
-                color: ColorPair::new(WHITE, BLACK),
+                color: ColorPair::new(color(&template.fg, WHITE), color(&template.bg, BLACK)),
                 glyph: to_cp437(template.glyph),
             },
             Name(template.name.clone()),
@@ -84,13 +291,32 @@ impl Templates {
             EntityType::Item => commands.add_component(entity, Item {}),
             EntityType::Enemy => {
                 commands.add_component(entity, Enemy {});
-                commands.add_component(entity, FieldOfView::new(6));
-                commands.add_component(entity, ChasingPlayer {});
+                commands.add_component(
+                    entity,
+                    FieldOfView::new(template.vision.unwrap_or(DEFAULT_VISION)),
+                );
+                match template.ai.unwrap_or(AiKind::Chase) {
+                    AiKind::Chase => commands.add_component(entity, ChasingPlayer {}),
+                    AiKind::Random => commands.add_component(entity, MovingRandomly {}),
+                    AiKind::Stationary => commands.add_component(entity, Stationary {}),
+                    AiKind::Ranged(range) => {
+                        commands.add_component(entity, RangedAttacker { range });
+                    }
+                    AiKind::Cowardly => commands.add_component(entity, Cowardly {}),
+                }
+                if template.opens_doors {
+                    commands.add_component(entity, OpensDoors {});
+                }
+                if let Some(movement) = template.movement {
+                    commands.add_component(entity, movement);
+                }
+                // Enemies without hit points are rejected when templates are loaded.
+                let hp = template.hp.unwrap_or(1);
                 commands.add_component(
                     entity,
                     Health {
-                        current: template.hp.unwrap(),
-                        max: template.hp.unwrap(),
+                        current: hp,
+                        max: hp,
                     },
                 );
             }
@@ -102,15 +328,112 @@ impl Templates {
                 .for_each(|(provides, n)| match provides.as_str() {
                     "Healing" => commands.add_component(entity, ProvideHealing { amount: *n }),
                     "MagicMap" => commands.add_component(entity, ProvidedDungeonMap {}),
-                    _ => println!("Warning!: We don't know what to provide"),
+                    _ => unreachable!("effects are checked when templates are loaded"),
                 });
         }
 
         if let Some(damage) = &template.base_damage {
-          commands.add_component(entity, Damage(*damage));
-          if template.entity_type == EntityType::Item {
-            commands.add_component(entity, Weapon {});
-          }
+            commands.add_component(entity, Damage(*damage));
+            if template.entity_type == EntityType::Item {
+                commands.add_component(entity, Weapon {});
+            }
+        }
+        entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goblin() -> Template {
+        ron::from_str(
+            r#"Template(
+                entity_type: Enemy,
+                name: "Goblin", glyph: 'g', levels: [0],
+                hp: Some(1),
+                frequency: 3,
+            )"#,
+        )
+        .unwrap()
+    }
+
+    fn potion() -> Template {
+        ron::from_str(
+            r#"Template(
+                entity_type: Item,
+                name: "Healing Potion", glyph: '!', levels: [0],
+                provides: Some([("Healing", 6)]),
+                frequency: 2,
+            )"#,
+        )
+        .unwrap()
+    }
+
+    /// Breaks one field of a template that is otherwise fine.
+    type Spoil = fn(&mut Template);
+
+    fn problems(entities: Vec<Template>) -> Vec<(usize, &'static str)> {
+        problem_locations(Templates { entities }.validate())
+    }
+
+    #[test]
+    fn shipped_templates_are_valid() {
+        Templates::load("resources/template.ron").unwrap();
+    }
+
+    #[test]
+    fn accepts_good_templates() {
+        assert!(problems(vec![goblin(), potion()]).is_empty());
+    }
+
+    #[test]
+    fn rejects_duplicate_and_empty_names() {
+        let mut nameless = goblin();
+        nameless.name = " ".to_string();
+        assert_eq!(
+            problems(vec![goblin(), goblin(), nameless]),
+            [(2, "name"), (3, "name")]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_enemies() {
+        let cases: [(Spoil, &str); 6] = [
+            (|t| t.levels.clear(), "levels"),
+            (|t| t.frequency = 0, "frequency"),
+            (|t| t.hp = None, "hp"),
+            (|t| t.fg = Some("green".to_string()), "fg"),
+            (|t| t.vision = Some(0), "vision"),
+            (|t| t.ai = Some(AiKind::Ranged(7)), "ai"),
+        ];
+        for (spoil, field) in cases {
+            let mut template = goblin();
+            spoil(&mut template);
+            assert_eq!(problems(vec![template]), [(1, field)]);
+        }
+    }
+
+    #[test]
+    fn rejects_enemy_fields_on_items() {
+        let cases: [(Spoil, &str); 5] = [
+            (|t| t.hp = Some(3), "hp"),
+            (|t| t.vision = Some(4), "vision"),
+            (|t| t.ai = Some(AiKind::Random), "ai"),
+            (|t| t.opens_doors = true, "opens_doors"),
+            (|t| t.movement = Some(Movement::Flies), "movement"),
+        ];
+        for (spoil, field) in cases {
+            let mut template = potion();
+            spoil(&mut template);
+            assert_eq!(problems(vec![template]), [(1, field)]);
         }
     }
+
+    #[test]
+    fn rejects_unknown_effects() {
+        let mut template = potion();
+        template.provides = Some(vec![("Flight".to_string(), 1)]);
+        assert_eq!(problems(vec![template]), [(1, "provides")]);
+    }
 }
@@ -8,23 +8,32 @@ use legion::{systems::CommandBuffer, world::SubWorld};
 #[read_component(Health)]
 #[read_component(FieldOfView)]
 #[read_component(Player)]
+#[read_component(Movement)]
+#[read_component(OpensDoors)]
 pub fn chasing(#[resource] map: &Map, ecs: &SubWorld, commands: &mut CommandBuffer) {
     let mut movers = <(Entity, &Point, &ChasingPlayer, &FieldOfView)>::query();
     let mut positions = <(Entity, &Point, &Health)>::query();
     let mut players = <(&Point, &Player)>::query();
 
-    let player_pos = players.iter(ecs).nth(0).unwrap().0;
-    let player_idx = map_idx(player_pos.x, player_pos.y);
-    let search_target = vec![player_idx];
-
-    let dijkistra_map = DijkstraMap::new(SCREEN_WIDTH, SCREEN_HEIGHT, &search_target, map, 1024.0);
+    let player_pos = players.iter(ecs).next().unwrap().0;
+    // Worked out when a chaser first needs one: doors are in the way of
+    // some monsters and not of others.
+    let mut through_doors = None;
+    let mut round_doors = None;
 
     movers.iter(ecs).for_each(|(entity, pos, _, fov)| {
         if !fov.visible_tiles.contains(player_pos) {
             return;
         }
-        let idx = map_idx(pos.x, pos.y);
-        if let Some(destination) = DijkstraMap::find_lowest_exit(&dijkistra_map, idx, map) {
+        let idx = map.map_idx(pos.x, pos.y);
+        let movement = super::movement::movement_of(ecs, *entity);
+        let opens_doors = super::movement::opens_doors(ecs, *entity);
+        let dijkstra_map = if opens_doors {
+            through_doors.get_or_insert_with(|| map.distances_from(*player_pos, true))
+        } else {
+            round_doors.get_or_insert_with(|| map.distances_from(*player_pos, false))
+        };
+        if let Some(destination) = map.lowest_exit_as(dijkstra_map, idx, movement, opens_doors) {
             let distance = DistanceAlg::Pythagoras.distance2d(*pos, *player_pos);
 
             let destination = if distance > 1.2 {
@@ -10,7 +10,8 @@ use crate::prelude::*;
 #[write_component(Health)]
 #[read_component(Carried)]
 #[read_component(Damage)]
-pub fn combat(ecs: &mut SubWorld, command: &mut CommandBuffer) {
+#[read_component(Name)]
+pub fn combat(ecs: &mut SubWorld, command: &mut CommandBuffer, #[resource] events: &mut Events) {
     let mut attackers = <(Entity, &WantsToAttact)>::query();
 
     let victims = attackers
@@ -18,7 +19,7 @@ pub fn combat(ecs: &mut SubWorld, command: &mut CommandBuffer) {
         .map(|(entity, attack)| (*entity, attack.attacker, attack.victim))
         .collect::<Vec<_>>();
 
-    victims.iter().for_each(|(message, attacker, victim)| {
+    for (message, attacker, victim) in &victims {
         let base_damage = if let Ok(v) = ecs.entry_ref(*attacker) {
             if let Ok(damage) = v.get_component::<Damage>() {
                 damage.0
@@ -42,17 +43,33 @@ pub fn combat(ecs: &mut SubWorld, command: &mut CommandBuffer) {
             .unwrap()
             .get_component::<Player>()
             .is_ok();
+        let attacker = Actor::of(ecs, *attacker);
+        let victim_actor = Actor::of(ecs, *victim);
 
         if let Ok(health) = ecs
             .entry_mut(*victim)
             .unwrap()
             .get_component_mut::<Health>()
         {
+            let was_alive = health.current > 0;
             health.current -= final_damage;
+            let killed = was_alive && health.current < 1;
+
+            events.send(GameEvent::DamageDealt {
+                attacker: attacker.clone(),
+                victim: victim_actor.clone(),
+                amount: final_damage,
+            });
+            if killed {
+                events.send(GameEvent::EntityDied {
+                    victim: victim_actor,
+                    killer: attacker,
+                });
+            }
             if health.current < 1 && !is_player {
                 command.remove(*victim);
             }
         }
         command.remove(*message);
-    });
+    }
 }
@@ -0,0 +1,57 @@
+use crate::prelude::*;
+use legion::{systems::CommandBuffer, world::SubWorld};
+
+#[system]
+#[read_component(Point)]
+#[read_component(Cowardly)]
+#[read_component(FieldOfView)]
+#[read_component(Health)]
+#[read_component(Player)]
+#[read_component(Movement)]
+pub fn cowardly(#[resource] map: &Map, ecs: &SubWorld, commands: &mut CommandBuffer) {
+    let mut cowards = <(Entity, &Point, &FieldOfView)>::query().filter(component::<Cowardly>());
+    let mut players = <(Entity, &Point)>::query().filter(component::<Player>());
+    let mut occupied = <&Point>::query().filter(component::<Health>());
+
+    let (player, player_pos) = players.iter(ecs).next().unwrap();
+
+    cowards
+        .iter(ecs)
+        .filter(|(_, _, fov)| fov.visible_tiles.contains(player_pos))
+        .for_each(|(entity, pos, _)| {
+            let distance = |pos: Point| DistanceAlg::Pythagoras.distance2d(pos, *player_pos);
+            let movement = super::movement::movement_of(ecs, *entity);
+
+            let escape = [
+                Point::new(-1, 0),
+                Point::new(1, 0),
+                Point::new(0, -1),
+                Point::new(0, 1),
+            ]
+            .into_iter()
+            .map(|delta| *pos + delta)
+            .filter(|step| map.can_enter_tile_as(*step, movement))
+            .filter(|step| !occupied.iter(ecs).any(|other| other == step))
+            .filter(|step| distance(*step) > distance(*pos))
+            .max_by(|a, b| distance(*a).total_cmp(&distance(*b)));
+
+            if let Some(destination) = escape {
+                commands.push((
+                    (),
+                    WantsToMove {
+                        entity: *entity,
+                        destination,
+                    },
+                ));
+            } else if distance(*pos) < 1.2 {
+                // Cornered: fight back.
+                commands.push((
+                    (),
+                    WantsToAttact {
+                        attacker: *entity,
+                        victim: *player,
+                    },
+                ));
+            }
+        });
+}
@@ -1,22 +1,39 @@
-use legion::world::SubWorld;
+use legion::{systems::CommandBuffer, world::SubWorld};
 
-#[warn(clippy::pedantic)]
 use crate::prelude::*;
 
+/// Health lost for each round spent standing in lava, unless flying.
+const LAVA_DAMAGE: i32 = 3;
+/// Health lost for each round spent wading through deep water.
+const DROWNING_DAMAGE: i32 = 1;
+
 #[system]
-#[read_component(Health)]
+#[write_component(Health)]
 #[read_component(Point)]
 #[read_component(Player)]
 #[read_component(AmuletOfYala)]
-pub fn end_turn(ecs: &SubWorld, #[resource] turn_state: &mut TurnState, #[resource] map: &Map) {
+#[read_component(Movement)]
+#[read_component(Name)]
+pub fn end_turn(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] turn_state: &mut TurnState,
+    #[resource] map: &Map,
+    #[resource] events: &mut Events,
+) {
     let mut player_hp = <(&Health, &Point)>::query().filter(component::<Player>());
     let mut amulet = <&Point>::query().filter(component::<AmuletOfYala>());
 
+    // Once a round, when everything has had its move.
+    if *turn_state == TurnState::MonsterTurn {
+        hurt_by_terrain(ecs, commands, map, events);
+    }
+
     let amulet_default = Point::new(-1, -1);
 
-    let amulet_pos = amulet.iter(ecs).nth(0).unwrap_or(&amulet_default);
+    let amulet_pos = amulet.iter(ecs).next().unwrap_or(&amulet_default);
 
-    let current_state = turn_state.clone();
+    let current_state = *turn_state;
 
     let mut new_state = match turn_state {
         TurnState::AwaitingInput => return,
@@ -34,10 +51,60 @@ pub fn end_turn(ecs: &SubWorld, #[resource] turn_state: &mut TurnState, #[resour
         }
 
         let idx = map.point2d_to_index(*pos);
-        if map.tiles[idx] == TileType::Exit {
-            new_state = TurnState::NextLevel
+        match map.tiles[idx] {
+            TileType::Exit => new_state = TurnState::NextLevel,
+            TileType::UpStairs => new_state = TurnState::PreviousLevel,
+            _ => {}
         }
     });
 
+    if new_state == TurnState::Victory {
+        events.send(GameEvent::PlayerWon);
+    }
     *turn_state = new_state;
 }
+
+/// Burns whatever stands in lava and drowns whatever walks into deep water.
+fn hurt_by_terrain(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    map: &Map,
+    events: &mut Events,
+) {
+    let hurt: Vec<(Entity, TileType, i32)> = <(Entity, &Point)>::query()
+        .filter(component::<Health>())
+        .iter(ecs)
+        .filter_map(|(entity, pos)| {
+            let tile = map.tiles[map.point2d_to_index(*pos)];
+            match (tile, super::movement::movement_of(ecs, *entity)) {
+                (TileType::Lava, Movement::Walks | Movement::Swims) => {
+                    Some((*entity, tile, LAVA_DAMAGE))
+                }
+                (TileType::DeepWater, Movement::Walks) => Some((*entity, tile, DROWNING_DAMAGE)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    for (entity, tile, amount) in hurt {
+        let victim = Actor::of(ecs, entity);
+        let Ok(mut entry) = ecs.entry_mut(entity) else {
+            continue;
+        };
+        let Ok(health) = entry.get_component_mut::<Health>() else {
+            continue;
+        };
+        let was_alive = health.current > 0;
+        health.current -= amount;
+        let killed = was_alive && health.current < 1;
+        if health.current < 1 && !victim.is_player {
+            commands.remove(entity);
+        }
+        events.send(GameEvent::HurtByTerrain {
+            victim,
+            tile,
+            amount,
+            killed,
+        });
+    }
+}
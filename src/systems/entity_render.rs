@@ -10,7 +10,7 @@ use crate::prelude::*;
 #[read_component(FieldOfView)]
 pub fn entity_render(ecs: &SubWorld, #[resource] camera: &Camera) {
     let mut fov = <&FieldOfView>::query().filter(component::<Player>());
-    let player_fov = fov.iter(ecs).nth(0).unwrap();
+    let player_fov = fov.iter(ecs).next().unwrap();
 
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(1);
@@ -18,7 +18,7 @@ pub fn entity_render(ecs: &SubWorld, #[resource] camera: &Camera) {
 
     <(&Point, &Render)>::query()
         .iter(ecs)
-        .filter(|(pos, _ )| player_fov.visible_tiles.contains(&pos) )
+        .filter(|(pos, _)| player_fov.visible_tiles.contains(pos))
         .for_each(|(pos, render)| {
             draw_batch.set(*pos - offset, render.color, render.glyph);
         });
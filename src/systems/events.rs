@@ -0,0 +1,7 @@
+use crate::prelude::*;
+
+/// Runs last in every schedule, once every system has had a chance to read the events.
+#[system]
+pub fn clear_events(#[resource] events: &mut Events) {
+    events.clear();
+}
@@ -7,7 +7,7 @@ use crate::prelude::*;
 #[write_component(FieldOfView)]
 pub fn field_of_view(ecs: &mut SubWorld, #[resource] map: &Map) {
     let mut views = <(&Point, &mut FieldOfView)>::query();
-    
+
     views
         .iter_mut(ecs)
         .filter(|(_, fov)| fov.is_dirty)
@@ -2,28 +2,33 @@ use legion::world::SubWorld;
 
 use crate::prelude::*;
 
+/// How many of the latest messages are shown under the map.
+const MESSAGE_LOG_LINES: usize = 5;
+
 #[system]
 #[read_component(Health)]
 #[read_component(Player)]
 #[read_component(Item)]
 #[read_component(Carried)]
 #[read_component(Name)]
-pub fn hud(ecs: &SubWorld) {
+pub fn hud(ecs: &SubWorld, #[resource] log: &MessageLog) {
     let mut health_query = <&Health>::query().filter(component::<Player>());
 
     <(Entity, &Player)>::query()
         .iter(ecs)
-        .find_map(|(entity, _)| Some(*entity))
+        .map(|(entity, _)| *entity)
+        .next()
         .unwrap();
 
     let mut item_query = <(&Item, &Name, &Carried)>::query();
 
     let (player, map_level) = <(Entity, &Player)>::query()
         .iter(ecs)
-        .find_map(|(entity, player)| Some((*entity, player.map_level)))
+        .map(|(entity, player)| (*entity, player.map_level))
+        .next()
         .unwrap();
 
-    if let Some(player_health) = health_query.iter(ecs).nth(0) {
+    if let Some(player_health) = health_query.iter(ecs).next() {
         let mut draw_batch = DrawBatch::new();
         draw_batch.target(2);
 
@@ -35,7 +40,7 @@ pub fn hud(ecs: &SubWorld) {
             player_health.max,
             ColorPair::new(RED, BLACK),
         );
-        
+
         // Health text overlay on the bar (centered)
         draw_batch.print_color_centered(
             0,
@@ -56,7 +61,7 @@ pub fn hud(ecs: &SubWorld) {
         // Instructions/controls at y=1 (below health bar)
         draw_batch.print_color(
             Point::new(1, 1),
-            "Arrow keys: Move | G: Pick up | 0-9: Use item",
+            "Arrow keys: Move | G: Pick up | 0-9: Use item | C: Close doors | L: Log",
             ColorPair::new(CYAN, BLACK),
         );
 
@@ -64,7 +69,7 @@ pub fn hud(ecs: &SubWorld) {
         let mut item_count = 0;
         let inventory_start_y = 3;
         let max_items_display = 8; // Limit items to prevent overlap with game area
-        
+
         item_query
             .iter(ecs)
             .filter(|(_, _, carried)| carried.0 == player)
@@ -88,6 +93,17 @@ pub fn hud(ecs: &SubWorld) {
             );
         }
 
+        // Most recent messages along the bottom, newest last
+        let recent = log.recent(MESSAGE_LOG_LINES);
+        let log_start_y = SCREEN_HEIGHT - recent.len() as i32;
+        for (y, message) in recent.iter().enumerate() {
+            draw_batch.print_color(
+                Point::new(1, log_start_y + y as i32),
+                &message.text,
+                ColorPair::new(message.kind.color(), BLACK),
+            );
+        }
+
         draw_batch.submit(10000).expect("Batch error");
     }
 }
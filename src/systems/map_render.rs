@@ -5,6 +5,7 @@ use legion::world::SubWorld;
 use crate::prelude::*;
 
 #[system]
+#[allow(clippy::borrowed_box)]
 #[read_component(FieldOfView)]
 #[read_component(Player)]
 pub fn map_render(
@@ -14,7 +15,7 @@ pub fn map_render(
     #[resource] theme: &Box<dyn MapThemes>,
 ) {
     let mut fov = <&FieldOfView>::query().filter(component::<Player>());
-    let player_fov = fov.iter(ecs).nth(0).unwrap();
+    let player_fov = fov.iter(ecs).next().unwrap();
 
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(0);
@@ -22,11 +23,11 @@ pub fn map_render(
         for x in camera.left_x..=camera.right_x {
             let pt = Point::new(x, y);
             let offset = Point::new(camera.left_x, camera.top_y);
-            let idx = map_idx(x, y);
+            let idx = map.map_idx(x, y);
             if map.in_bound(pt) && player_fov.visible_tiles.contains(&pt) | map.revealed_tiles[idx]
             {
                 let tint = if player_fov.visible_tiles.contains(&pt) {
-                    WHITE
+                    terrain_tint(map.tiles[idx])
                 } else {
                     DARK_GRAY
                 };
@@ -38,3 +39,16 @@ pub fn map_render(
     }
     draw_batch.submit(0).expect("Batch error");
 }
+
+/// Water, lava and the like keep their color while in view.
+fn terrain_tint(tile: TileType) -> (u8, u8, u8) {
+    match tile {
+        TileType::ShallowWater => CYAN,
+        TileType::DeepWater => ROYAL_BLUE,
+        TileType::Lava => ORANGE_RED,
+        TileType::Rubble => GRAY,
+        TileType::Bridge => SADDLE_BROWN,
+        TileType::Grass => FOREST_GREEN,
+        _ => WHITE,
+    }
+}
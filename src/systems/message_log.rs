@@ -0,0 +1,109 @@
+use crate::prelude::*;
+
+/// Turns this schedule's events into lines in the message log.
+#[system]
+pub fn message_log(#[resource] events: &Events, #[resource] log: &mut MessageLog) {
+    for event in events.iter() {
+        match event {
+            GameEvent::DamageDealt {
+                attacker,
+                victim,
+                amount,
+            } => {
+                if *amount < 1 {
+                    if victim.is_player {
+                        log.add(MessageKind::Info, format!("{} misses you.", attacker.name));
+                    } else if attacker.is_player {
+                        log.add(MessageKind::Info, format!("You miss the {}.", victim.name));
+                    }
+                } else if victim.is_player {
+                    log.add(
+                        MessageKind::Hurt,
+                        format!("{} hits you for {amount}.", attacker.name),
+                    );
+                } else if attacker.is_player {
+                    log.add(
+                        MessageKind::Attack,
+                        format!("You hit the {} for {amount}.", victim.name),
+                    );
+                }
+            }
+            GameEvent::EntityDied { victim, killer } => {
+                let text = if victim.is_player {
+                    format!("{} kills you.", killer.name)
+                } else if killer.is_player {
+                    format!("You kill the {}.", victim.name)
+                } else {
+                    format!("The {} dies.", victim.name)
+                };
+                log.add(MessageKind::Death, text);
+            }
+            GameEvent::ItemPickedUp { actor, item } if actor.is_player => {
+                log.add(MessageKind::Item, format!("You pick up the {}.", item.name));
+            }
+            GameEvent::ItemDiscarded { actor, item } if actor.is_player => {
+                log.add(MessageKind::Item, format!("You discard the {}.", item.name));
+            }
+            GameEvent::ItemUsed {
+                actor,
+                item,
+                effects,
+            } if actor.is_player => {
+                let verb = match effects.first() {
+                    Some(ItemEffect::Healed(_)) => "drink",
+                    Some(ItemEffect::RevealedMap) => "read",
+                    None => "use",
+                };
+                log.add(MessageKind::Item, format!("You {verb} the {}.", item.name));
+                for effect in effects {
+                    let text = match effect {
+                        ItemEffect::Healed(amount) => format!("You recover {amount} health."),
+                        ItemEffect::RevealedMap => "The layout of this level is revealed.".into(),
+                    };
+                    log.add(MessageKind::Item, text);
+                }
+            }
+            GameEvent::HurtByTerrain {
+                victim,
+                tile,
+                amount,
+                killed,
+            } => {
+                let text = match (tile, victim.is_player, killed) {
+                    (TileType::Lava, true, false) => format!("The lava burns you for {amount}."),
+                    (TileType::Lava, true, true) => "The lava burns you to death.".to_string(),
+                    (TileType::Lava, false, true) => {
+                        format!("The {} burns up in the lava.", victim.name)
+                    }
+                    (_, true, false) => format!("You swallow water, and lose {amount}."),
+                    (_, true, true) => "You drown.".to_string(),
+                    (_, false, true) => format!("The {} drowns.", victim.name),
+                    (_, false, false) => continue,
+                };
+                let kind = if *killed {
+                    MessageKind::Death
+                } else {
+                    MessageKind::Hurt
+                };
+                log.add(kind, text);
+            }
+            GameEvent::DoorOpened { actor } if actor.is_player => {
+                log.add(MessageKind::Info, "You open the door.");
+            }
+            GameEvent::DoorClosed { actor } if actor.is_player => {
+                log.add(MessageKind::Info, "You close the door.");
+            }
+            GameEvent::LevelEntered { depth, revisited } => {
+                let verb = if *revisited { "return to" } else { "enter" };
+                log.add(
+                    MessageKind::Info,
+                    format!("You {verb} level {}.", depth + 1),
+                );
+            }
+            GameEvent::PlayerWon => {
+                log.add(MessageKind::Info, "You put on the Amulet of Yala.");
+            }
+            _ => {}
+        }
+    }
+}
@@ -2,60 +2,87 @@ use crate::prelude::*;
 
 mod chasing;
 mod combat;
+mod cowardly;
 mod end_turn;
 mod entity_render;
+mod events;
 mod fov;
 mod hud;
 mod map_render;
+mod message_log;
 mod movement;
 mod player_input;
 mod random_move;
+mod ranged;
+mod stationary;
 mod tooltip;
 mod use_item;
 
-pub fn build_input_scheduler() -> Schedule {
-    Schedule::builder()
+// With `render` off the schedules skip every system that draws to a console,
+// so the same game logic can be simulated without a window.
+pub fn build_input_scheduler(render: bool) -> Schedule {
+    let mut builder = Schedule::builder();
+    builder
         .add_system(player_input::player_input_system())
         .add_system(fov::field_of_view_system())
-        .flush()
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(hud::hud_system())
-        .add_system(tooltip::tooltips_system())
-        .build()
+        .flush();
+    if render {
+        builder
+            .add_system(map_render::map_render_system())
+            .add_system(entity_render::entity_render_system());
+    }
+    builder.add_system(message_log::message_log_system());
+    if render {
+        builder
+            .add_system(hud::hud_system())
+            .add_system(tooltip::tooltips_system());
+    }
+    builder.add_system(events::clear_events_system()).build()
 }
 
-pub fn build_player_scheduler() -> Schedule {
-    Schedule::builder()
+pub fn build_player_scheduler(render: bool) -> Schedule {
+    let mut builder = Schedule::builder();
+    builder
         .add_system(use_item::use_item_system())
         .add_system(combat::combat_system())
-        .flush()
-        .add_system(movement::movement_system())
-        .flush()
-        .add_system(fov::field_of_view_system())
-        .flush()
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(hud::hud_system())
-        .add_system(end_turn::end_turn_system())
-        .build()
+        .flush();
+    add_turn_systems(&mut builder, render);
+    builder.build()
 }
 
-pub fn build_monster_scheduler() -> Schedule {
-    Schedule::builder()
+pub fn build_monster_scheduler(render: bool) -> Schedule {
+    let mut builder = Schedule::builder();
+    builder
         .add_system(random_move::random_move_system())
         .add_system(chasing::chasing_system())
+        .add_system(stationary::stationary_system())
+        .add_system(ranged::ranged_system())
+        .add_system(cowardly::cowardly_system())
         .flush()
         .add_system(use_item::use_item_system())
         .add_system(combat::combat_system())
-        .flush()
+        .flush();
+    add_turn_systems(&mut builder, render);
+    builder.build()
+}
+
+// Movement, vision and end-of-turn bookkeeping shared by the player and monster turns.
+fn add_turn_systems(builder: &mut legion::systems::Builder, render: bool) {
+    builder
         .add_system(movement::movement_system())
         .flush()
         .add_system(fov::field_of_view_system())
-        .flush()
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(hud::hud_system())
+        .flush();
+    if render {
+        builder
+            .add_system(map_render::map_render_system())
+            .add_system(entity_render::entity_render_system());
+    }
+    builder
         .add_system(end_turn::end_turn_system())
-        .build()
+        .add_system(message_log::message_log_system());
+    if render {
+        builder.add_system(hud::hud_system());
+    }
+    builder.add_system(events::clear_events_system());
 }
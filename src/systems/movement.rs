@@ -1,34 +1,48 @@
 use legion::{systems::CommandBuffer, world::SubWorld};
 
-#[warn(clippy::pedantic)]
 use crate::prelude::*;
 
 #[system(for_each)]
 #[read_component(Player)]
 #[read_component(FieldOfView)]
+#[read_component(OpensDoors)]
+#[read_component(Movement)]
+#[read_component(Name)]
 pub fn movement(
     entity: &Entity,
     want_move: &WantsToMove,
     #[resource] map: &mut Map,
     #[resource] camera: &mut Camera,
+    #[resource] events: &mut Events,
     ecs: &mut SubWorld,
     command: &mut CommandBuffer,
 ) {
-    if let Ok(entry) = ecs.entry_ref(want_move.entity) {
-        if let Ok(fov) = entry.get_component::<FieldOfView>() {
-            command.add_component(want_move.entity, fov.clone_dirty());
+    if let Ok(entry) = ecs.entry_ref(want_move.entity)
+        && let Ok(fov) = entry.get_component::<FieldOfView>()
+    {
+        command.add_component(want_move.entity, fov.clone_dirty());
 
-            if entry.get_component::<Player>().is_ok() {
-                camera.on_player_move(want_move.destination);
+        if entry.get_component::<Player>().is_ok() {
+            camera.on_player_move(want_move.destination);
 
-                fov.visible_tiles
-                    .iter()
-                    .for_each(|pos| map.revealed_tiles[map_idx(pos.x, pos.y)] = true);
-            }
+            fov.visible_tiles.iter().for_each(|pos| {
+                let idx = map.map_idx(pos.x, pos.y);
+                map.revealed_tiles[idx] = true;
+            });
         }
     }
 
-    if map.can_enter_tile(want_move.destination) {
+    if map.is_closed_door(want_move.destination) {
+        // Bumping a door opens it, and takes the move.
+        if opens_doors(ecs, want_move.entity) {
+            let idx = map.point2d_to_index(want_move.destination);
+            map.tiles[idx] = TileType::OpenDoor;
+            refresh_views(ecs, command);
+            events.send(GameEvent::DoorOpened {
+                actor: Actor::of(ecs, want_move.entity),
+            });
+        }
+    } else if map.can_enter_tile_as(want_move.destination, movement_of(ecs, want_move.entity)) {
         command.add_component(want_move.entity, want_move.destination);
 
         if ecs
@@ -42,3 +56,26 @@ pub fn movement(
     }
     command.remove(*entity);
 }
+
+/// How `entity` gets about; walking, unless it says otherwise.
+pub(super) fn movement_of(ecs: &SubWorld, entity: Entity) -> Movement {
+    ecs.entry_ref(entity)
+        .ok()
+        .and_then(|entry| entry.get_component::<Movement>().ok().copied())
+        .unwrap_or_default()
+}
+
+/// Whether `entity` can bump doors open: the player can, and monsters
+/// whose templates say so.
+pub(super) fn opens_doors(ecs: &SubWorld, entity: Entity) -> bool {
+    ecs.entry_ref(entity).is_ok_and(|entry| {
+        entry.get_component::<Player>().is_ok() || entry.get_component::<OpensDoors>().is_ok()
+    })
+}
+
+/// Has every field of view worked out again, after a door opened or closed.
+pub(super) fn refresh_views(ecs: &SubWorld, commands: &mut CommandBuffer) {
+    <(Entity, &FieldOfView)>::query()
+        .iter(ecs)
+        .for_each(|(entity, fov)| commands.add_component(*entity, fov.clone_dirty()));
+}
@@ -9,20 +9,27 @@ use legion::{systems::CommandBuffer, world::SubWorld};
 #[read_component(Carried)]
 #[read_component(Item)]
 #[read_component(Weapon)]
+#[read_component(Name)]
+#[read_component(FieldOfView)]
 pub fn player_input(
     ecs: &mut SubWorld,
     commands: &mut CommandBuffer,
     #[resource] key: &Option<VirtualKeyCode>,
+    #[resource] map: &mut Map,
     #[resource] turn_state: &mut TurnState,
+    #[resource] replay: &mut Replay,
+    #[resource] events: &mut Events,
 ) {
     let mut players = <(Entity, &Point)>::query().filter(component::<Player>());
-    
+
     if let Some(key) = *key {
+        replay.record(key);
         let delta = match key {
             VirtualKeyCode::G => {
                 let (player, player_pos) = players
                     .iter(ecs)
-                    .find_map(|(entity, pos)| Some((*entity, *pos)))
+                    .map(|(entity, pos)| (*entity, *pos))
+                    .next()
                     .unwrap();
 
                 let mut item = <(Entity, &Item, &Point)>::query();
@@ -31,19 +38,28 @@ pub fn player_input(
                     .for_each(|(entity, _, _)| {
                         commands.remove_component::<Point>(*entity);
                         commands.add_component(*entity, Carried(player));
-                        if let Ok(e) = ecs.entry_ref(*entity) {
-                            if e.get_component::<Weapon>().is_ok() {
-                                <(Entity, &Carried, &Weapon)>::query()
-                                    .iter(ecs)
-                                    .filter(|(_, c, _)| c.0 == player)
-                                    .for_each(|(e, _, _)| {
-                                        commands.remove(*e);
+                        events.send(GameEvent::ItemPickedUp {
+                            actor: Actor::of(ecs, player),
+                            item: Actor::of(ecs, *entity),
+                        });
+                        if let Ok(e) = ecs.entry_ref(*entity)
+                            && e.get_component::<Weapon>().is_ok()
+                        {
+                            <(Entity, &Carried, &Weapon)>::query()
+                                .iter(ecs)
+                                .filter(|(_, c, _)| c.0 == player)
+                                .for_each(|(e, _, _)| {
+                                    commands.remove(*e);
+                                    events.send(GameEvent::ItemDiscarded {
+                                        actor: Actor::of(ecs, player),
+                                        item: Actor::of(ecs, *e),
                                     });
-                            }
+                                });
                         };
                     });
                 Point::new(0, 0)
             }
+            VirtualKeyCode::C => close_doors(ecs, commands, map, events),
             VirtualKeyCode::Left => Point::new(-1, 0),
             VirtualKeyCode::Right => Point::new(1, 0),
             VirtualKeyCode::Up => Point::new(0, -1),
@@ -63,7 +79,8 @@ pub fn player_input(
 
         let (player_entity, destination) = players
             .iter(ecs)
-            .find_map(|(entity, pos)| Some((*entity, *pos + delta)))
+            .map(|(entity, pos)| (*entity, *pos + delta))
+            .next()
             .unwrap();
 
         let mut enemies = <(Entity, &Point)>::query().filter(component::<Enemy>());
@@ -98,10 +115,52 @@ pub fn player_input(
     }
 }
 
+/// Closes the open doors next to the player that nothing is standing in.
+fn close_doors(
+    ecs: &SubWorld,
+    commands: &mut CommandBuffer,
+    map: &mut Map,
+    events: &mut Events,
+) -> Point {
+    let (player, player_pos) = <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .map(|(entity, pos)| (*entity, *pos))
+        .next()
+        .unwrap();
+    let occupied: Vec<Point> = <&Point>::query().iter(ecs).copied().collect();
+
+    let mut closed = false;
+    for delta in [
+        Point::new(-1, 0),
+        Point::new(1, 0),
+        Point::new(0, -1),
+        Point::new(0, 1),
+    ] {
+        let pos = player_pos + delta;
+        if let Some(idx) = map.try_idx(pos)
+            && map.tiles[idx] == TileType::OpenDoor
+            && !occupied.contains(&pos)
+        {
+            map.tiles[idx] = TileType::Door;
+            closed = true;
+        }
+    }
+    if closed {
+        super::movement::refresh_views(ecs, commands);
+        events.send(GameEvent::DoorClosed {
+            actor: Actor::of(ecs, player),
+        });
+    }
+
+    Point::zero()
+}
+
 fn use_item(n: usize, ecs: &mut SubWorld, commands: &mut CommandBuffer) -> Point {
     let player_entity = <(Entity, &Player)>::query()
         .iter(ecs)
-        .find_map(|(entity, _)| Some(*entity))
+        .map(|(entity, _)| *entity)
+        .next()
         .unwrap();
 
     let item_entity = <(Entity, &Item, &Carried)>::query()
@@ -109,7 +168,8 @@ fn use_item(n: usize, ecs: &mut SubWorld, commands: &mut CommandBuffer) -> Point
         .filter(|(_, _, carried)| carried.0 == player_entity)
         .enumerate()
         .filter(|(item_count, (_, _, _))| *item_count == n)
-        .find_map(|(_, (entity, _, _))| Some(*entity));
+        .map(|(_, (entity, _, _))| *entity)
+        .next();
 
     if let Some(item_entity) = item_entity {
         commands.push((
@@ -0,0 +1,55 @@
+use crate::prelude::*;
+use legion::{systems::CommandBuffer, world::SubWorld};
+
+#[system]
+#[read_component(Point)]
+#[read_component(RangedAttacker)]
+#[read_component(FieldOfView)]
+#[read_component(Player)]
+#[read_component(Movement)]
+#[read_component(OpensDoors)]
+pub fn ranged(#[resource] map: &Map, ecs: &SubWorld, commands: &mut CommandBuffer) {
+    let mut shooters = <(Entity, &Point, &RangedAttacker, &FieldOfView)>::query();
+    let mut players = <(Entity, &Point)>::query().filter(component::<Player>());
+
+    let (player, player_pos) = players.iter(ecs).next().unwrap();
+    let mut through_doors = None;
+    let mut round_doors = None;
+
+    shooters
+        .iter(ecs)
+        .filter(|(_, _, _, fov)| fov.visible_tiles.contains(player_pos))
+        .for_each(|(entity, pos, ranged, _)| {
+            let distance = DistanceAlg::Pythagoras.distance2d(*pos, *player_pos);
+            if distance <= ranged.range as f32 {
+                commands.push((
+                    (),
+                    WantsToAttact {
+                        attacker: *entity,
+                        victim: *player,
+                    },
+                ));
+                return;
+            }
+
+            // Out of range: close in, the same way a chaser would.
+            let idx = map.map_idx(pos.x, pos.y);
+            let movement = super::movement::movement_of(ecs, *entity);
+            let opens_doors = super::movement::opens_doors(ecs, *entity);
+            let dijkstra_map = if opens_doors {
+                through_doors.get_or_insert_with(|| map.distances_from(*player_pos, true))
+            } else {
+                round_doors.get_or_insert_with(|| map.distances_from(*player_pos, false))
+            };
+            if let Some(destination) = map.lowest_exit_as(dijkstra_map, idx, movement, opens_doors)
+            {
+                commands.push((
+                    (),
+                    WantsToMove {
+                        entity: *entity,
+                        destination: map.index_to_point2d(destination),
+                    },
+                ));
+            }
+        });
+}
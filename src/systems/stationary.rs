@@ -0,0 +1,26 @@
+use crate::prelude::*;
+use legion::{systems::CommandBuffer, world::SubWorld};
+
+#[system]
+#[read_component(Point)]
+#[read_component(Stationary)]
+#[read_component(Player)]
+pub fn stationary(ecs: &SubWorld, commands: &mut CommandBuffer) {
+    let mut guards = <(Entity, &Point)>::query().filter(component::<Stationary>());
+    let mut players = <(Entity, &Point)>::query().filter(component::<Player>());
+
+    let (player, player_pos) = players.iter(ecs).next().unwrap();
+
+    guards
+        .iter(ecs)
+        .filter(|(_, pos)| DistanceAlg::Pythagoras.distance2d(**pos, *player_pos) < 1.2)
+        .for_each(|(entity, _)| {
+            commands.push((
+                (),
+                WantsToAttact {
+                    attacker: *entity,
+                    victim: *player,
+                },
+            ));
+        });
+}
@@ -6,8 +6,16 @@ use legion::{systems::CommandBuffer, world::SubWorld};
 #[read_component(ProvidedDungeonMap)]
 #[write_component(Health)]
 #[read_component(ActivateItem)]
-pub fn use_item(ecs: &mut SubWorld, commands: &mut CommandBuffer, #[resource] map: &mut Map) {
-    let mut healing_to_apply = Vec::<(Entity, i32)>::new();
+#[read_component(Name)]
+#[read_component(Player)]
+pub fn use_item(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &mut Map,
+    #[resource] events: &mut Events,
+) {
+    let mut healing_to_apply = Vec::<(usize, Entity, i32)>::new();
+    let mut used = Vec::<(Actor, Actor, Vec<ItemEffect>)>::new();
 
     <(Entity, &ActivateItem)>::query()
         .iter(ecs)
@@ -15,24 +23,43 @@ pub fn use_item(ecs: &mut SubWorld, commands: &mut CommandBuffer, #[resource] ma
             let item = ecs.entry_ref(activate.item);
 
             if let Ok(item) = item {
+                let mut effects = Vec::new();
+
                 if let Ok(healing) = item.get_component::<ProvideHealing>() {
-                    healing_to_apply.push((activate.used_by, healing.amount));
+                    healing_to_apply.push((used.len(), activate.used_by, healing.amount));
                 }
 
-                if let Ok(_) = item.get_component::<ProvidedDungeonMap>() {
+                if item.get_component::<ProvidedDungeonMap>().is_ok() {
                     map.revealed_tiles.iter_mut().for_each(|tile| *tile = true);
+                    effects.push(ItemEffect::RevealedMap);
                 }
+
+                used.push((
+                    Actor::of(ecs, activate.used_by),
+                    Actor::of(ecs, activate.item),
+                    effects,
+                ));
             }
 
             commands.remove(activate.item);
             commands.remove(*entity);
         });
 
-    for heal in healing_to_apply {
-        if let Ok(mut target) = ecs.entry_mut(heal.0) {
-            if let Ok(health) = target.get_component_mut::<Health>() {
-                health.current = i32::min(health.max, health.current + heal.1)
-            }
+    for (use_idx, target, amount) in healing_to_apply {
+        if let Ok(mut target) = ecs.entry_mut(target)
+            && let Ok(health) = target.get_component_mut::<Health>()
+        {
+            let healed = i32::min(health.max, health.current + amount) - health.current;
+            health.current += healed;
+            used[use_idx].2.insert(0, ItemEffect::Healed(healed));
         }
     }
+
+    for (actor, item, effects) in used {
+        events.send(GameEvent::ItemUsed {
+            actor,
+            item,
+            effects,
+        });
+    }
 }
@@ -1,13 +1,17 @@
 #![warn(clippy::pedantic)]
 
+use serde::{Deserialize, Serialize};
+
 use crate::prelude::*;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TurnState {
     AwaitingInput,
     PlayerTurn,
     MonsterTurn,
     GameOver,
-    Victory, 
+    Victory,
     NextLevel,
+    PreviousLevel,
+    ShowingLog,
 }
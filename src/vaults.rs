@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+pub const DEFAULT_VAULTS: &str = "resources/vaults.ron";
+
+/// Vaults have to fit inside the outer wall of the smallest map `--map-size`
+/// allows, whichever way they are turned.
+pub const MAX_VAULT_SIZE: usize = 18;
+
+/// What a character in a vault's rows stands for.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub enum VaultTile {
+    Wall,
+    Floor,
+    /// A closed door.
+    Door,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Rubble,
+    Bridge,
+    Grass,
+    /// Floor with a monster rolled from the templates for the level.
+    Monster,
+    /// Floor with an item rolled from the templates for the level.
+    Item,
+    /// Floor with the named template on it, whatever the level.
+    Template(String),
+    /// Floor with the level's goal on it: the stairs down, or the amulet.
+    Exit,
+}
+
+/// A hand-drawn room stamped into generated levels.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Vault {
+    pub name: String,
+    /// The vault's tiles, top row first, one character per tile.
+    pub rows: Vec<String>,
+    pub legend: BTreeMap<char, VaultTile>,
+    /// The shallowest depth it is placed at, counting from 0 like template
+    /// levels.
+    #[serde(default)]
+    pub min_depth: u32,
+    /// The deepest depth it is placed at; no limit if left out.
+    pub max_depth: Option<u32>,
+    /// Placed one time in `rarity` when it is picked for a level.
+    pub rarity: u32,
+    /// Whether it may be turned a quarter, half or three quarters round.
+    #[serde(default)]
+    pub rotate: bool,
+    /// Whether it may be flipped left to right.
+    #[serde(default)]
+    pub mirror: bool,
+}
+
+impl Vault {
+    pub fn fits_depth(&self, depth: u32) -> bool {
+        depth >= self.min_depth && self.max_depth.is_none_or(|max| depth <= max)
+    }
+}
+
+/// Every vault levels can be given.
+#[derive(Clone, Debug, Deserialize)]
+pub struct VaultLibrary {
+    pub vaults: Vec<Vault>,
+}
+
+impl VaultLibrary {
+    /// Reads the vaults at `path` and validates them against `templates`.
+    pub fn load(path: &str, templates: &Templates) -> Result<Self, DataFileError> {
+        let library: VaultLibrary = read_data_file(path)?;
+        let problems = library.validate(templates);
+        valid(library, problems)
+    }
+
+    /// Every problem that would otherwise surface when a vault is placed, in
+    /// file order.
+    pub fn validate(&self, templates: &Templates) -> Vec<Problem> {
+        let mut problems = Problems::new("entry");
+        let mut first_entry_named = HashMap::new();
+        let template_names: HashSet<&str> = templates
+            .entities
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect();
+
+        for (idx, vault) in self.vaults.iter().enumerate() {
+            let entry = idx + 1;
+            let mut problem = problems.entry(entry, Some(&vault.name));
+
+            if vault.name.trim().is_empty() {
+                problem.add("name", "must not be empty".to_string());
+            } else if let Some(first) = first_entry_named.get(&vault.name) {
+                problem.add("name", format!("duplicates the name of entry {first}"));
+            } else {
+                first_entry_named.insert(vault.name.clone(), entry);
+            }
+
+            let width = vault.rows.first().map_or(0, |row| row.chars().count());
+            let mut rectangular = false;
+            if width == 0 {
+                problem.add("rows", "must have at least one tile".to_string());
+            } else if let Some(row) = vault
+                .rows
+                .iter()
+                .position(|row| row.chars().count() != width)
+            {
+                problem.add(
+                    "rows",
+                    format!("row {} isn't as wide as the first row", row + 1),
+                );
+            } else {
+                rectangular = true;
+            }
+            if width.max(vault.rows.len()) > MAX_VAULT_SIZE {
+                problem.add(
+                    "rows",
+                    format!("must be at most {MAX_VAULT_SIZE} tiles across and down"),
+                );
+            }
+
+            let unknown: BTreeSet<char> = vault
+                .rows
+                .iter()
+                .flat_map(|row| row.chars())
+                .filter(|c| !vault.legend.contains_key(c))
+                .collect();
+            if rectangular && unknown.is_empty() {
+                for (x, y) in sealed_spawns(vault) {
+                    problem.add(
+                        "rows",
+                        format!(
+                            "row {}, column {} can't be walked to from the vault's edge",
+                            y + 1,
+                            x + 1
+                        ),
+                    );
+                }
+            }
+            for c in unknown {
+                problem.add(
+                    "legend",
+                    format!("'{c}' is used in the rows but not listed"),
+                );
+            }
+
+            let exits = vault
+                .rows
+                .iter()
+                .flat_map(|row| row.chars())
+                .filter(|c| vault.legend.get(c) == Some(&VaultTile::Exit))
+                .count();
+            if exits > 1 {
+                problem.add(
+                    "legend",
+                    format!("has {exits} exits, but a level has one goal"),
+                );
+            }
+            for tile in vault.legend.values() {
+                if let VaultTile::Template(name) = tile
+                    && !template_names.contains(name.as_str())
+                {
+                    problem.add("legend", format!("unknown template \"{name}\""));
+                }
+            }
+
+            if let Some(max) = vault.max_depth
+                && max < vault.min_depth
+            {
+                problem.add(
+                    "max_depth",
+                    format!("is {max}, but min_depth is {}", vault.min_depth),
+                );
+            }
+
+            if vault.rarity < 1 {
+                problem.add("rarity", "is 0, but must be at least 1".to_string());
+            }
+        }
+
+        problems.into_vec()
+    }
+
+    /// The vaults that can be placed at `depth`.
+    pub fn for_depth(&self, depth: u32) -> Vec<Vault> {
+        self.vaults
+            .iter()
+            .filter(|vault| vault.fits_depth(depth))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Where the vault's exit, monsters and items are walled off from its edge,
+/// as columns and rows counting from 0. Only walls block the way; closed
+/// doors are opened and terrain is crossed, if at a cost. The vault's rows
+/// must all be as wide and only use characters in its legend.
+fn sealed_spawns(vault: &Vault) -> Vec<(usize, usize)> {
+    let rows: Vec<Vec<&VaultTile>> = vault
+        .rows
+        .iter()
+        .map(|row| row.chars().map(|c| &vault.legend[&c]).collect())
+        .collect();
+    let (width, height) = (rows[0].len(), rows.len());
+    let walkable = |x: usize, y: usize| *rows[y][x] != VaultTile::Wall;
+
+    let mut reached = vec![vec![false; width]; height];
+    let mut open: Vec<(usize, usize)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| x == 0 || y == 0 || x == width - 1 || y == height - 1)
+        .filter(|&(x, y)| walkable(x, y))
+        .collect();
+    open.iter().for_each(|&(x, y)| reached[y][x] = true);
+    while let Some((x, y)) = open.pop() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (x, y) in neighbours {
+            if x < width && y < height && !reached[y][x] && walkable(x, y) {
+                reached[y][x] = true;
+                open.push((x, y));
+            }
+        }
+    }
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| !reached[y][x])
+        .filter(|&(x, y)| {
+            matches!(
+                rows[y][x],
+                VaultTile::Exit | VaultTile::Monster | VaultTile::Item | VaultTile::Template(_)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(rows: &[&str]) -> Vault {
+        let rows: Vec<String> = rows.iter().map(|row| format!("{row:?}")).collect();
+        ron::from_str(&format!(
+            r#"Vault(
+                name: "Test",
+                rows: [{}],
+                legend: {{ '-': Floor, '#': Wall, '+': Door, '~': DeepWater, 'M': Monster,
+                    '!': Item, 'o': Template("Orc"), '>': Exit }},
+                rarity: 1,
+            )"#,
+            rows.join(", ")
+        ))
+        .unwrap()
+    }
+
+    fn problems(vaults: Vec<Vault>) -> Vec<(usize, &'static str)> {
+        let templates = Templates::load("resources/template.ron").unwrap();
+        problem_locations(VaultLibrary { vaults }.validate(&templates))
+    }
+
+    #[test]
+    fn shipped_vaults_are_valid() {
+        let templates = Templates::load("resources/template.ron").unwrap();
+        VaultLibrary::load(DEFAULT_VAULTS, &templates).unwrap();
+    }
+
+    #[test]
+    fn accepts_spawns_behind_doors_and_water() {
+        let rows = ["-----", "-#+#-", "-#M#-", "-~~~-", "-~>~-", "-#o#-"];
+        assert!(problems(vec![vault(&rows)]).is_empty());
+    }
+
+    #[test]
+    fn rejects_spawns_walled_off_from_the_edge() {
+        for sealed in ['>', 'M', '!', 'o'] {
+            let middle = format!("-#{sealed}#-");
+            let rows = ["-###-", middle.as_str(), "-###-"];
+            assert_eq!(problems(vec![vault(&rows)]), [(1, "rows")], "{sealed}");
+        }
+    }
+
+    #[test]
+    fn rejects_ragged_oversized_and_unlisted_rows() {
+        let wide = "-".repeat(MAX_VAULT_SIZE + 1);
+        let mut oversized = vault(&[&wide]);
+        oversized.name = "Oversized".to_string();
+        let mut unlisted = vault(&["-?-"]);
+        unlisted.name = "Unlisted".to_string();
+        assert_eq!(
+            problems(vec![vault(&["---", "--"]), oversized, unlisted]),
+            [(1, "rows"), (2, "rows"), (3, "legend")]
+        );
+    }
+
+    #[test]
+    fn rejects_two_exits_and_unknown_templates() {
+        let mut unknown = vault(&["-o-"]);
+        unknown.name = "Unknown".to_string();
+        unknown
+            .legend
+            .insert('o', VaultTile::Template("Dragon".to_string()));
+        assert_eq!(
+            problems(vec![vault(&["->->-"]), unknown]),
+            [(1, "legend"), (2, "legend")]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_names_depths_and_rarity() {
+        let mut nameless = vault(&["-"]);
+        nameless.name = String::new();
+        let mut backwards = vault(&["-"]);
+        backwards.min_depth = 2;
+        backwards.max_depth = Some(1);
+        let mut never = vault(&["-"]);
+        never.name = "Never".to_string();
+        never.rarity = 0;
+        assert_eq!(
+            problems(vec![nameless, backwards.clone(), backwards, never]),
+            [
+                (1, "name"),
+                (2, "max_depth"),
+                (3, "name"),
+                (3, "max_depth"),
+                (4, "rarity")
+            ]
+        );
+    }
+}